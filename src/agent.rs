@@ -0,0 +1,344 @@
+use std::{future::Future, sync::Arc};
+
+use async_stream::stream;
+use futures::StreamExt;
+use serde_json::Value;
+use sync_wrapper::SyncFuture;
+
+use crate::{
+    completion::CompletionAccumulator,
+    message::Message,
+    tool::{Tool, ToolRegistry},
+    ChatModel, Completion, Error, ModelOptions, Prompt, Result, Stream, StreamingChatModel,
+    ToolCall, ToolDefinition, Usage,
+};
+
+pub struct Agent<M> {
+    model: Arc<M>,
+    prompt: Prompt,
+    options: ModelOptions,
+    tools: ToolRegistry,
+    max_iterations: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct AgentOutput {
+    pub completion: Completion,
+    pub transcript: Prompt,
+    /// Summed over every model call of the run.
+    pub usage: Usage,
+}
+
+#[derive(Clone, Debug)]
+pub struct ToolResult {
+    pub tool_call: ToolCall,
+    pub content: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum AgentEvent {
+    Delta(Completion),
+    ToolCall(ToolCall),
+    ToolResult(ToolResult),
+    Final(AgentOutput),
+}
+
+impl<M: Send + Sync + 'static> Agent<M> {
+    pub fn new(model: M) -> Self {
+        Self {
+            model: Arc::new(model),
+            prompt: Prompt::new(),
+            options: ModelOptions::default(),
+            tools: ToolRegistry::new(),
+            max_iterations: 10,
+        }
+    }
+
+    pub fn prompt(mut self, prompt: Prompt) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    pub fn options<T: Into<ModelOptions>>(mut self, options: T) -> Self {
+        self.options = options.into();
+        self
+    }
+
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn register<T: Tool>(mut self, tool: T) -> Self {
+        self.tools = self.tools.register(tool);
+        self
+    }
+
+    pub fn tool<F, Fut>(mut self, definition: ToolDefinition, handler: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<String>> + Send + 'static,
+    {
+        self.tools = self.tools.function(definition, handler);
+        self
+    }
+
+    fn call_options(&self, defaults: &ModelOptions) -> Result<ModelOptions> {
+        if self.tools.is_empty() {
+            return Ok(self.options.clone());
+        }
+        self.options
+            .clone()
+            .with_tools(defaults, self.tools.definitions())
+    }
+}
+
+impl<M: ChatModel> Agent<M> {
+    pub async fn run(&self) -> Result<AgentOutput> {
+        let mut transcript = self.prompt.clone();
+        let options = self.call_options(self.model.options())?;
+        let mut usage = Usage::default();
+        for _ in 0..self.max_iterations {
+            let completion =
+                ChatModel::completion(&*self.model, &transcript, options.clone()).await?;
+            usage += completion.usage.unwrap_or_default();
+            if !step(&mut transcript, &completion) {
+                return Ok(AgentOutput {
+                    completion,
+                    transcript,
+                    usage,
+                });
+            }
+            for tool_call in completion.tool_calls.iter().flatten() {
+                let content = self.tools.call(tool_call).await;
+                transcript.push(Message::tool(&tool_call.id, content));
+            }
+        }
+        Err(Error::MaxIterations(self.max_iterations))
+    }
+}
+
+impl<M: StreamingChatModel> Agent<M> {
+    /// Runs the loop as the stream is polled; dropping the stream cancels the run.
+    pub fn run_stream(&self) -> Stream<Result<AgentEvent>> {
+        let model = self.model.clone();
+        let mut transcript = self.prompt.clone();
+        let options = self.call_options(self.model.options());
+        let tools = self.tools.clone();
+        let max_iterations = self.max_iterations;
+        let stream = stream! {
+            let options = match options {
+                Ok(options) => options,
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            };
+            let mut usage = Usage::default();
+            for _ in 0..max_iterations {
+                // The futures are only polled, never shared, but `Stream` has to be `Sync`.
+                let request = StreamingChatModel::stream(&*model, &transcript, options.clone());
+                let mut stream = match SyncFuture::new(request).await {
+                        Ok(stream) => stream,
+                        Err(err) => {
+                            yield Err(err);
+                            return;
+                        }
+                    };
+                let mut accumulator = CompletionAccumulator::new();
+                while let Some(item) = stream.next().await {
+                    let item = match item {
+                        Ok(item) => item,
+                        Err(err) => {
+                            yield Err(err);
+                            return;
+                        }
+                    };
+                    accumulator.push(item.clone());
+                    yield Ok(AgentEvent::Delta(item));
+                }
+                let completion = match accumulator.finish() {
+                    Ok(completion) => completion,
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                };
+                usage += completion.usage.unwrap_or_default();
+                if !step(&mut transcript, &completion) {
+                    yield Ok(AgentEvent::Final(AgentOutput {
+                        completion,
+                        transcript,
+                        usage,
+                    }));
+                    return;
+                }
+                for tool_call in completion.tool_calls.iter().flatten() {
+                    yield Ok(AgentEvent::ToolCall(tool_call.clone()));
+                    let content = SyncFuture::new(tools.call(tool_call)).await;
+                    transcript.push(Message::tool(&tool_call.id, &content));
+                    yield Ok(AgentEvent::ToolResult(ToolResult {
+                        tool_call: tool_call.clone(),
+                        content,
+                    }));
+                }
+            }
+            yield Err(Error::MaxIterations(max_iterations));
+        };
+        stream.into()
+    }
+}
+
+/// Appends the assistant turn to `transcript`, returning whether the model asked for tool calls.
+fn step(transcript: &mut Prompt, completion: &Completion) -> bool {
+    let message = Message::from(completion.clone());
+    let tool_calls = matches!(message, Message::ToolCalls(_));
+    transcript.push(message);
+    tool_calls
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    use crate::Model;
+
+    use super::*;
+
+    struct Scripted {
+        options: ModelOptions,
+        completions: Mutex<Vec<Completion>>,
+    }
+
+    impl Scripted {
+        fn new(mut completions: Vec<Completion>) -> Self {
+            completions.reverse();
+            Self {
+                options: ModelOptions::openai().into(),
+                completions: Mutex::new(completions),
+            }
+        }
+
+        fn next(&self) -> Completion {
+            self.completions.lock().unwrap().pop().unwrap()
+        }
+    }
+
+    impl Model for Scripted {
+        fn options(&self) -> &ModelOptions {
+            &self.options
+        }
+    }
+
+    #[async_trait]
+    impl ChatModel for Scripted {
+        async fn completion(&self, _: &Prompt, _: ModelOptions) -> Result<Completion> {
+            Ok(self.next())
+        }
+    }
+
+    #[async_trait]
+    impl StreamingChatModel for Scripted {
+        async fn stream(&self, _: &Prompt, _: ModelOptions) -> Result<Stream<Result<Completion>>> {
+            Ok(futures::stream::iter([Ok(self.next())]).into())
+        }
+    }
+
+    fn script() -> Vec<Completion> {
+        let usage = Usage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+            ..Default::default()
+        };
+        vec![
+            Completion {
+                tool_calls: Some(vec![ToolCall::new("call_0", "add", r#"{"a":1,"b":2}"#)]),
+                usage: Some(usage),
+                ..Default::default()
+            },
+            Completion {
+                content: Some("3".to_owned()),
+                usage: Some(usage),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn agent(completions: Vec<Completion>) -> Agent<Scripted> {
+        Agent::new(Scripted::new(completions))
+            .prompt(Prompt::create("1 + 2 = ?"))
+            .tool(ToolDefinition::new("add"), async |arguments| {
+                let sum = arguments["a"].as_i64().unwrap() + arguments["b"].as_i64().unwrap();
+                Ok(json!(sum).to_string())
+            })
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let output = agent(script()).run().await.unwrap();
+        assert_eq!(output.completion.content.as_deref(), Some("3"));
+        assert_eq!(output.transcript.len(), 4);
+        assert_eq!(output.usage.total_tokens, 30);
+        let json = serde_json::to_value(&output.transcript[2]).unwrap();
+        assert_eq!(
+            json,
+            json!({"role": "tool", "content": "3", "tool_call_id": "call_0"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_stream() {
+        let mut stream = agent(script()).run_stream();
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event.unwrap());
+        }
+        assert!(matches!(&events[1], AgentEvent::ToolCall(tool_call) if tool_call.name() == "add"));
+        assert!(matches!(&events[2], AgentEvent::ToolResult(result) if result.content == "3"));
+        assert!(matches!(&events[4], AgentEvent::Final(output) if output.transcript.len() == 4));
+    }
+
+    #[test]
+    fn test_call_options() {
+        let defaults = ModelOptions::from(ModelOptions::openai().tools(vec![
+            ToolDefinition::new("search"),
+            ToolDefinition::new("add"),
+        ]));
+        let agent = agent(Vec::new());
+        let options = agent.call_options(&defaults).unwrap();
+        let names = defaults
+            .merge(&options)
+            .tools()
+            .unwrap()
+            .iter()
+            .map(|tool| tool.name().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["search", "add"]);
+        assert!(matches!(
+            agent.call_options(&ModelOptions::default()),
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_stream_without_runtime() {
+        let mut stream = agent(script()).run_stream();
+        let event = futures::executor::block_on(stream.next()).unwrap().unwrap();
+        assert!(matches!(event, AgentEvent::Delta(_)));
+    }
+
+    #[tokio::test]
+    async fn test_max_iterations() {
+        let result = agent(script()).max_iterations(1).run().await;
+        assert!(matches!(result, Err(Error::MaxIterations(1))));
+    }
+}
@@ -1,29 +1,261 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
 use serde::{Deserialize, Serialize};
 
-use crate::usage::Usage;
+use crate::{
+    reasoning::ReasoningBlock,
+    tool::{ToolCall, ToolCallAccumulator, ToolCallDelta},
+    usage::Usage,
+    Result,
+};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Why the model stopped, normalised across providers; unrecognised reasons are kept verbatim.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    Stop,
+    Length,
+    ToolCalls,
+    ContentFilter,
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl FinishReason {
+    /// Providers that report a plain stop when the turn ends in tool calls are brought in line
+    /// with OpenAI.
+    pub(crate) fn with_tool_calls(self, tool_calls: bool) -> Self {
+        match self {
+            Self::Stop if tool_calls => Self::ToolCalls,
+            reason => reason,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    /// UTF-8 bytes of the token, for tokens that are not valid UTF-8 on their own.
+    #[serde(default)]
+    pub bytes: Option<Vec<u8>>,
+    /// The most likely alternatives at this position, when `top_logprobs` was requested.
+    #[serde(default)]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f64,
+    #[serde(default)]
+    pub bytes: Option<Vec<u8>>,
+}
+
+/// One of several candidates returned for a request, e.g. with `n > 1`.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Choice {
+    pub index: u32,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub reasoning_content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub tool_call_deltas: Option<Vec<ToolCallDelta>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+}
+
+impl From<Choice> for Completion {
+    fn from(choice: Choice) -> Self {
+        Completion {
+            content: choice.content,
+            reasoning_content: choice.reasoning_content,
+            tool_calls: choice.tool_calls,
+            tool_call_deltas: choice.tool_call_deltas,
+            logprobs: choice.logprobs,
+            finish_reason: choice.finish_reason,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
 pub struct Completion {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Unix timestamp in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub created: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
     #[serde(default)]
     pub content: Option<String>,
     #[serde(default)]
     pub reasoning_content: Option<String>,
+    /// The provider's signature over the latest reasoning, required to send it back.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reasoning_signature: Option<String>,
+    /// The reasoning in the blocks it was returned in, for providers that take it back only as
+    /// is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reasoning_blocks: Option<Vec<ReasoningBlock>>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub tool_call_deltas: Option<Vec<ToolCallDelta>>,
+    /// Per-token log probabilities of `content`, when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+    /// Every choice, when more than one was returned; the fields above describe the one with
+    /// index 0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub choices: Option<Vec<Choice>>,
     #[serde(default)]
     pub usage: Option<Usage>,
 }
 
+#[derive(Default)]
+pub(crate) struct CompletionAccumulator {
+    id: Option<String>,
+    model: Option<String>,
+    created: Option<u64>,
+    system_fingerprint: Option<String>,
+    content: Option<String>,
+    reasoning_content: Option<String>,
+    reasoning_signature: Option<String>,
+    reasoning_blocks: Option<Vec<ReasoningBlock>>,
+    tool_calls: Option<Vec<ToolCall>>,
+    tool_call_accumulator: ToolCallAccumulator,
+    logprobs: Option<Vec<TokenLogprob>>,
+    finish_reason: Option<FinishReason>,
+    choices: BTreeMap<u32, CompletionAccumulator>,
+    usage: Option<Usage>,
+}
+
+impl CompletionAccumulator {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&mut self, item: Completion) {
+        self.id = self.id.take().or(item.id);
+        self.model = self.model.take().or(item.model);
+        self.created = self.created.or(item.created);
+        self.system_fingerprint = self.system_fingerprint.take().or(item.system_fingerprint);
+        if let Some(content_chunk) = item.content {
+            *self.content.get_or_insert_default() += content_chunk.as_str();
+        }
+        if let Some(reasoning_content_chunk) = item.reasoning_content {
+            *self.reasoning_content.get_or_insert_default() += reasoning_content_chunk.as_str();
+        }
+        // Signatures arrive whole, one per block of reasoning.
+        if let Some(reasoning_signature) = item.reasoning_signature {
+            self.reasoning_signature = Some(reasoning_signature);
+        }
+        if let Some(reasoning_blocks_chunk) = item.reasoning_blocks {
+            self.reasoning_blocks
+                .get_or_insert_with(Vec::new)
+                .extend(reasoning_blocks_chunk);
+        }
+        if let Some(tool_calls_chunk) = item.tool_calls {
+            self.tool_calls
+                .get_or_insert_with(Vec::new)
+                .extend(tool_calls_chunk);
+        }
+        for tool_call_delta in item.tool_call_deltas.iter().flatten() {
+            self.tool_call_accumulator.push(tool_call_delta);
+        }
+        if let Some(logprobs_chunk) = item.logprobs {
+            self.logprobs
+                .get_or_insert_with(Vec::new)
+                .extend(logprobs_chunk);
+        }
+        // Index 0 is accumulated in the fields above; interleaved deltas of the other choices are
+        // demultiplexed by index.
+        for choice in item.choices.into_iter().flatten() {
+            if choice.index > 0 {
+                self.choices
+                    .entry(choice.index)
+                    .or_default()
+                    .push(choice.into());
+            }
+        }
+        if let Some(finish_reason) = item.finish_reason {
+            self.finish_reason = Some(finish_reason);
+        }
+        if let Some(usage_chunk) = item.usage {
+            self.usage = Some(usage_chunk);
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<Completion> {
+        let mut tool_calls = self.tool_calls;
+        if !self.tool_call_accumulator.is_empty() {
+            tool_calls
+                .get_or_insert_with(Vec::new)
+                .extend(self.tool_call_accumulator.finish()?);
+        }
+        let finish_reason = self
+            .finish_reason
+            .map(|reason| reason.with_tool_calls(tool_calls.is_some()));
+        let mut completion = Completion {
+            id: self.id,
+            model: self.model,
+            created: self.created,
+            system_fingerprint: self.system_fingerprint,
+            content: self.content,
+            reasoning_content: self.reasoning_content,
+            reasoning_signature: self.reasoning_signature,
+            reasoning_blocks: self.reasoning_blocks,
+            tool_calls,
+            logprobs: self.logprobs,
+            finish_reason,
+            usage: self.usage,
+            ..Default::default()
+        };
+        if !self.choices.is_empty() {
+            let mut choices = vec![completion.choice(0)];
+            for (index, accumulator) in self.choices {
+                choices.push(accumulator.finish()?.choice(index));
+            }
+            completion.choices = Some(choices);
+        }
+        Ok(completion)
+    }
+}
+
 impl Completion {
-    pub(crate) fn new(
-        content: Option<String>,
-        reasoning_content: Option<String>,
-        usage: Option<Usage>,
-    ) -> Self {
-        Self {
-            content,
-            reasoning_content,
-            usage,
+    fn choice(&self, index: u32) -> Choice {
+        Choice {
+            index,
+            content: self.content.clone(),
+            reasoning_content: self.reasoning_content.clone(),
+            tool_calls: self.tool_calls.clone(),
+            tool_call_deltas: None,
+            logprobs: self.logprobs.clone(),
+            finish_reason: self.finish_reason.clone(),
         }
     }
 }
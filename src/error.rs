@@ -0,0 +1,285 @@
+use std::{fmt::Display, time::Duration};
+
+use reqwest::{header::HeaderMap, StatusCode};
+use serde_json::Value;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("rate limited: {0}")]
+    RateLimit(ApiError),
+    #[error("authentication failed: {0}")]
+    Authentication(ApiError),
+    #[error("context length exceeded: {0}")]
+    ContextLengthExceeded(ApiError),
+    /// The prompt was estimated not to fit before it was sent.
+    #[error("prompt of about {tokens} tokens does not fit the context window of {context_window}")]
+    ContextWindowExceeded {
+        tokens: usize,
+        context_window: usize,
+    },
+    #[error("content filtered: {0}")]
+    ContentFilter(ApiError),
+    #[error("request failed: {0}")]
+    Api(ApiError),
+    #[error("network error: {0}")]
+    Network(reqwest::Error),
+    #[error("malformed response: {0}")]
+    Decode(String),
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("agent did not produce a final answer within {0} iterations")]
+    MaxIterations(usize),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl Error {
+    /// Classifies an error reported by a provider by its status code and error code/type.
+    pub fn api(error: ApiError) -> Self {
+        let status = error.status.map(|status| status.as_u16());
+        let code = error.code.as_deref().unwrap_or_default();
+        let kind = error.kind.as_deref().unwrap_or_default();
+        let message = error.message.to_lowercase();
+        let is = |pattern: &str| code.contains(pattern) || kind.contains(pattern);
+        if status == Some(429) || is("rate_limit") || is("RESOURCE_EXHAUSTED") {
+            Self::RateLimit(error)
+        } else if matches!(status, Some(401 | 403))
+            || is("authentication")
+            || is("permission")
+            || is("invalid_api_key")
+            || is("UNAUTHENTICATED")
+            || is("PERMISSION_DENIED")
+        {
+            Self::Authentication(error)
+        } else if is("context_length")
+            || message.contains("context length")
+            || message.contains("context window")
+            || message.contains("prompt is too long")
+        {
+            Self::ContextLengthExceeded(error)
+        } else if is("content_filter") || is("content_policy") {
+            Self::ContentFilter(error)
+        } else {
+            Self::Api(error)
+        }
+    }
+
+    pub(crate) fn decode(message: impl Display) -> Self {
+        Self::Decode(message.to_string())
+    }
+
+    pub(crate) fn invalid_request(message: impl Display) -> Self {
+        Self::InvalidRequest(message.to_string())
+    }
+
+    /// The error body returned by the provider, if the request reached it.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::RateLimit(error)
+            | Self::Authentication(error)
+            | Self::ContextLengthExceeded(error)
+            | Self::ContentFilter(error)
+            | Self::Api(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Network(err) => err.status(),
+            _ => self.api_error().and_then(|error| error.status),
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.api_error().and_then(|error| error.retry_after)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_decode() {
+            Self::decode(err)
+        } else {
+            Self::Network(err)
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::decode(err)
+    }
+}
+
+/// An error reported by a provider, either as a non-success response or as an error event in the
+/// middle of a stream, in which case there is no status code.
+#[derive(Clone, Default, Debug)]
+#[non_exhaustive]
+pub struct ApiError {
+    pub status: Option<StatusCode>,
+    pub code: Option<String>,
+    pub kind: Option<String>,
+    pub message: String,
+    pub retry_after: Option<Duration>,
+}
+
+impl ApiError {
+    /// For errors raised by a custom `Provider`; pass `None` as the status for errors that did not
+    /// come with a response, such as one in the middle of a stream.
+    pub fn new<T: AsRef<str>>(status: impl Into<Option<StatusCode>>, message: T) -> Self {
+        Self {
+            status: status.into(),
+            message: message.as_ref().to_owned(),
+            ..Default::default()
+        }
+    }
+
+    pub fn code<T: AsRef<str>>(mut self, code: T) -> Self {
+        self.code = Some(code.as_ref().to_owned());
+        self
+    }
+
+    /// The error type, e.g. `rate_limit_error`.
+    pub fn kind<T: AsRef<str>>(mut self, kind: T) -> Self {
+        self.kind = Some(kind.as_ref().to_owned());
+        self
+    }
+
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = Some(retry_after);
+        self
+    }
+
+    pub(crate) async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let retry_after = retry_after(response.headers());
+        let body = response.text().await.unwrap_or_default();
+        let mut error = match serde_json::from_str::<Value>(&body) {
+            Ok(value) => match value.get("error") {
+                Some(error) => Self::from_value(error),
+                None => Self::from_value(&value),
+            },
+            Err(_) => Self::default(),
+        };
+        if error.message.is_empty() {
+            error.message = body;
+        }
+        error.status = Some(status);
+        error.retry_after = error.retry_after.or(retry_after);
+        error
+    }
+
+    /// Reads the `error` object of an OpenAI, Anthropic or Gemini error body, or the plain error
+    /// string of Ollama.
+    pub(crate) fn from_value(error: &Value) -> Self {
+        let string = |value: Option<&Value>| match value? {
+            Value::String(value) => Some(value.clone()),
+            Value::Null => None,
+            value => Some(value.to_string()),
+        };
+        if let Value::String(message) = error {
+            return Self {
+                message: message.clone(),
+                ..Default::default()
+            };
+        }
+        let retry_after = error
+            .get("details")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .find_map(|detail| detail.get("retryDelay")?.as_str()?.strip_suffix('s'))
+            .and_then(|delay| delay.parse().ok())
+            .map(Duration::from_secs_f64);
+        Self {
+            status: None,
+            code: string(error.get("code")),
+            kind: string(error.get("type")).or_else(|| string(error.get("status"))),
+            message: string(error.get("message")).unwrap_or_default(),
+            retry_after,
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(status) = self.status {
+            write!(f, "status code {status}: ")?;
+        }
+        f.write_str(&self.message)?;
+        match (&self.kind, &self.code) {
+            (Some(kind), Some(code)) => write!(f, " ({kind}, {code})"),
+            (Some(kind), None) => write!(f, " ({kind})"),
+            (None, Some(code)) => write!(f, " ({code})"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Reads `retry-after-ms` or the delay-seconds form of `Retry-After`.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name)?.to_str().ok()?.trim().parse::<f64>().ok();
+    header("retry-after-ms")
+        .map(|ms| Duration::from_secs_f64(ms / 1000.0))
+        .or_else(|| header("retry-after").map(Duration::from_secs_f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let openai = ApiError::from_value(&json!({
+            "message": "This model's maximum context length is 8192 tokens.",
+            "type": "invalid_request_error",
+            "code": "context_length_exceeded",
+        }));
+        assert!(matches!(
+            Error::api(openai),
+            Error::ContextLengthExceeded(ApiError { code: Some(code), .. }) if code == "context_length_exceeded"
+        ));
+        let anthropic = ApiError::from_value(&json!({
+            "type": "authentication_error",
+            "message": "invalid x-api-key",
+        }));
+        assert!(matches!(Error::api(anthropic), Error::Authentication(_)));
+        let gemini = ApiError::from_value(&json!({
+            "code": 429,
+            "message": "Resource has been exhausted",
+            "status": "RESOURCE_EXHAUSTED",
+            "details": [{"@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "12s"}],
+        }));
+        let error = Error::api(gemini);
+        assert!(matches!(error, Error::RateLimit(_)));
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(12)));
+        let ollama = Error::api(ApiError::from_value(&json!("model 'qwen' not found")));
+        assert_eq!(ollama.to_string(), "request failed: model 'qwen' not found");
+    }
+
+    #[test]
+    fn test_new() {
+        let error = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "busy")
+            .code("overloaded")
+            .kind("server_error")
+            .retry_after(Duration::from_secs(5));
+        assert_eq!(
+            error.to_string(),
+            "status code 503 Service Unavailable: busy (server_error, overloaded)"
+        );
+        let error = Error::api(error);
+        assert_eq!(error.status(), Some(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(5)));
+        let error = Error::api(ApiError::new(None, "slow down").kind("rate_limit_error"));
+        assert!(matches!(
+            error,
+            Error::RateLimit(ApiError { status: None, .. })
+        ));
+    }
+}
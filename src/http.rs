@@ -0,0 +1,213 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, LazyLock, OnceLock},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{retry::millis, Error, Result};
+
+static DEFAULT_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+
+/// How requests to a provider are sent.
+///
+/// Without a client of its own, one is built from the timeouts, proxy and user agent the first
+/// time it is needed and shared by every clone of these options; when nothing is configured the
+/// process-wide default client is used.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct HttpOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, with = "millis::option")]
+    pub connect_timeout: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, with = "millis::option")]
+    pub read_timeout: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, with = "millis::option")]
+    pub timeout: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(skip)]
+    client: Option<reqwest::Client>,
+    #[serde(skip)]
+    built: Arc<OnceLock<reqwest::Client>>,
+}
+
+impl HttpOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connect_timeout(self, connect_timeout: Duration) -> Self {
+        Self {
+            connect_timeout: Some(connect_timeout),
+            ..self.rebuild()
+        }
+    }
+
+    pub fn read_timeout(self, read_timeout: Duration) -> Self {
+        Self {
+            read_timeout: Some(read_timeout),
+            ..self.rebuild()
+        }
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self.rebuild()
+        }
+    }
+
+    pub fn proxy<T: AsRef<str>>(self, proxy: T) -> Self {
+        Self {
+            proxy: Some(proxy.as_ref().to_owned()),
+            ..self.rebuild()
+        }
+    }
+
+    pub fn user_agent<T: AsRef<str>>(self, user_agent: T) -> Self {
+        Self {
+            user_agent: Some(user_agent.as_ref().to_owned()),
+            ..self.rebuild()
+        }
+    }
+
+    pub fn header<K: AsRef<str>, V: AsRef<str>>(mut self, name: K, value: V) -> Self {
+        self.headers
+            .insert(name.as_ref().to_owned(), value.as_ref().to_owned());
+        self
+    }
+
+    /// Sends requests through `client`, ignoring the timeouts, proxy and user agent; headers are
+    /// still added to every request.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    fn rebuild(self) -> Self {
+        Self {
+            built: Default::default(),
+            ..self
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        self.connect_timeout.is_none()
+            && self.read_timeout.is_none()
+            && self.timeout.is_none()
+            && self.proxy.is_none()
+            && self.user_agent.is_none()
+    }
+
+    fn build(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(read_timeout) = self.read_timeout {
+            builder = builder.read_timeout(read_timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy).map_err(Error::invalid_request)?);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        builder.build().map_err(Error::invalid_request)
+    }
+
+    pub(crate) fn get_client(&self) -> Result<reqwest::Client> {
+        if let Some(client) = &self.client {
+            return Ok(client.clone());
+        }
+        if self.is_default() {
+            return Ok(DEFAULT_CLIENT.clone());
+        }
+        if let Some(client) = self.built.get() {
+            return Ok(client.clone());
+        }
+        let client = self.build()?;
+        Ok(self.built.get_or_init(|| client).clone())
+    }
+}
+
+impl Debug for HttpOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpOptions")
+            .field("connect_timeout", &self.connect_timeout)
+            .field("read_timeout", &self.read_timeout)
+            .field("timeout", &self.timeout)
+            .field("proxy", &self.proxy)
+            .field("user_agent", &self.user_agent)
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("client", &self.client.is_some())
+            .finish()
+    }
+}
+
+/// Starts a POST request to `url` with the configured client and extra headers.
+pub(crate) fn post(http: Option<&HttpOptions>, url: &str) -> Result<reqwest::RequestBuilder> {
+    let Some(http) = http else {
+        return Ok(DEFAULT_CLIENT.post(url));
+    };
+    let mut request = http.get_client()?.post(url);
+    for (name, value) in &http.headers {
+        request = request.header(name, value);
+    }
+    Ok(request)
+}
+
+/// Starts a GET request to `url` with the configured client. The extra headers are meant for the
+/// provider, so they are left out.
+pub(crate) fn get(http: Option<&HttpOptions>, url: &str) -> Result<reqwest::RequestBuilder> {
+    match http {
+        Some(http) => Ok(http.get_client()?.get(url)),
+        None => Ok(DEFAULT_CLIENT.get(url)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_client_is_shared() {
+        let http = HttpOptions::new().timeout(Duration::from_secs(5));
+        let clone = http.clone();
+        http.get_client().unwrap();
+        assert!(clone.built.get().is_some());
+        let http = clone.user_agent("agentx");
+        assert!(http.built.get().is_none());
+        assert!(HttpOptions::new().proxy("::").get_client().is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let http: HttpOptions = serde_json::from_value(json!({
+            "connect_timeout": 1500,
+            "headers": { "x-trace": "1" },
+        }))
+        .unwrap();
+        assert_eq!(http.connect_timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(
+            serde_json::to_value(&http).unwrap(),
+            json!({ "connect_timeout": 1500, "headers": { "x-trace": "1" } })
+        );
+    }
+}
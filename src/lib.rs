@@ -1,18 +1,40 @@
+pub mod agent;
 pub mod completion;
+pub mod error;
+pub mod http;
 pub mod message;
 pub mod models;
 pub mod options;
+pub mod pricing;
 pub mod prompt;
+pub mod provider;
+pub mod reasoning;
+pub mod response_format;
+pub mod retry;
+pub mod tokenizer;
+pub mod tool;
 pub mod usage;
 
-pub use completion::Completion;
+pub use agent::{Agent, AgentEvent, AgentOutput};
+pub use completion::{Choice, Completion, FinishReason, TokenLogprob, TopLogprob};
+pub use error::{ApiError, Error, Result};
+pub use http::HttpOptions;
 pub use message::{Message, Role};
 pub use models::{
-    Model, Stream,
     chat::{ChatModel, StreamingChatModel},
+    Model, Stream,
+};
+pub use options::{
+    AnthropicModelOptions, GeminiModelOptions, ModelOptions, OllamaModelOptions, OpenAIModelOptions,
 };
-pub use options::{ModelOptions, OpenAIModelOptions};
+pub use pricing::{Cost, Price, PriceTable};
 pub use prompt::Prompt;
-pub use usage::Usage;
+pub use provider::Provider;
+pub use reasoning::{Reasoning, ReasoningBlock, ReasoningEffort};
+pub use response_format::ResponseFormat;
+pub use retry::RetryPolicy;
+pub use tokenizer::{Approximate, Bpe, Tokenizer};
+pub use tool::{Tool, ToolCall, ToolCallAccumulator, ToolCallDelta, ToolDefinition, ToolRegistry};
+pub use usage::{CompletionTokensDetails, PromptTokensDetails, Usage};
 
 pub use futures::stream::StreamExt;
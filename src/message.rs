@@ -3,12 +3,15 @@ use serde::ser::SerializeMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{Display, Formatter};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+use crate::{reasoning::ReasoningBlock, tool::ToolCall, Completion};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum Role {
     System,
     User,
     Assistant,
+    Tool,
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -158,6 +161,8 @@ impl<'de> Deserialize<'de> for Media {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum Message {
+    Tool(ToolMessage),
+    ToolCalls(ToolCallsMessage),
     Text(TextMessage),
     Media(MediaMessage),
 }
@@ -170,12 +175,29 @@ impl Message {
     pub fn media(role: Role) -> MediaMessage {
         MediaMessage::new(role)
     }
+
+    pub fn tool<I: AsRef<str>, T: AsRef<str>>(tool_call_id: I, content: T) -> Self {
+        Message::Tool(ToolMessage::new(tool_call_id, content))
+    }
+
+    pub fn tool_calls(tool_calls: Vec<ToolCall>) -> ToolCallsMessage {
+        ToolCallsMessage::new(tool_calls)
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct TextMessage {
-    role: Role,
-    content: String,
+    pub(crate) role: Role,
+    pub(crate) content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) reasoning_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) reasoning_signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) reasoning_blocks: Option<Vec<ReasoningBlock>>,
 }
 
 impl TextMessage {
@@ -183,14 +205,23 @@ impl TextMessage {
         TextMessage {
             role,
             content: content.as_ref().to_owned(),
+            reasoning_content: None,
+            reasoning_signature: None,
+            reasoning_blocks: None,
         }
     }
+
+    /// The model's reasoning behind this turn; whether it is sent back depends on the provider.
+    pub fn reasoning_content<T: AsRef<str>>(mut self, reasoning_content: T) -> Self {
+        self.reasoning_content = Some(reasoning_content.as_ref().to_owned());
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct MediaMessage {
-    role: Role,
-    content: Vec<Media>,
+    pub(crate) role: Role,
+    pub(crate) content: Vec<Media>,
 }
 
 impl MediaMessage {
@@ -211,6 +242,8 @@ impl MediaMessage {
         self
     }
 
+    /// A remote URL or a `data:` URL. Ollama only takes inline images, so remote ones are
+    /// downloaded before the request is sent.
     pub fn image_url<T: AsRef<str>>(mut self, url: T) -> Self {
         self.content.push(Media::ImageUrl(url.as_ref().to_owned()));
         self
@@ -237,6 +270,114 @@ impl From<MediaMessage> for Message {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ToolMessage {
+    pub(crate) role: Role,
+    pub(crate) content: String,
+    pub(crate) tool_call_id: String,
+}
+
+impl ToolMessage {
+    pub fn new<I: AsRef<str>, T: AsRef<str>>(tool_call_id: I, content: T) -> Self {
+        ToolMessage {
+            role: Role::Tool,
+            content: content.as_ref().to_owned(),
+            tool_call_id: tool_call_id.as_ref().to_owned(),
+        }
+    }
+
+    pub fn tool_call_id(&self) -> &str {
+        &self.tool_call_id
+    }
+}
+
+impl From<ToolMessage> for Message {
+    fn from(message: ToolMessage) -> Self {
+        Message::Tool(message)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ToolCallsMessage {
+    pub(crate) role: Role,
+    #[serde(default)]
+    pub(crate) content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) reasoning_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) reasoning_signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) reasoning_blocks: Option<Vec<ReasoningBlock>>,
+    pub(crate) tool_calls: Vec<ToolCall>,
+}
+
+impl ToolCallsMessage {
+    pub fn new(tool_calls: Vec<ToolCall>) -> Self {
+        ToolCallsMessage {
+            role: Role::Assistant,
+            content: None,
+            reasoning_content: None,
+            reasoning_signature: None,
+            reasoning_blocks: None,
+            tool_calls,
+        }
+    }
+
+    pub fn content<T: AsRef<str>>(mut self, content: T) -> Self {
+        self.content = Some(content.as_ref().to_owned());
+        self
+    }
+
+    /// The model's reasoning behind this turn; whether it is sent back depends on the provider.
+    pub fn reasoning_content<T: AsRef<str>>(mut self, reasoning_content: T) -> Self {
+        self.reasoning_content = Some(reasoning_content.as_ref().to_owned());
+        self
+    }
+
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.tool_calls
+    }
+}
+
+impl From<ToolCallsMessage> for Message {
+    fn from(message: ToolCallsMessage) -> Self {
+        Message::ToolCalls(message)
+    }
+}
+
+/// The assistant turn of a completion, keeping its reasoning and tool calls for the next request.
+impl From<Completion> for Message {
+    fn from(completion: Completion) -> Self {
+        let content = completion.content.filter(|content| !content.is_empty());
+        let reasoning_content = completion
+            .reasoning_content
+            .filter(|reasoning_content| !reasoning_content.is_empty());
+        let reasoning_signature = completion.reasoning_signature;
+        let reasoning_blocks = completion.reasoning_blocks;
+        match completion
+            .tool_calls
+            .filter(|tool_calls| !tool_calls.is_empty())
+        {
+            Some(tool_calls) => Message::ToolCalls(ToolCallsMessage {
+                content,
+                reasoning_content,
+                reasoning_signature,
+                reasoning_blocks,
+                ..ToolCallsMessage::new(tool_calls)
+            }),
+            None => Message::Text(TextMessage {
+                reasoning_content,
+                reasoning_signature,
+                reasoning_blocks,
+                ..TextMessage::new(Role::Assistant, content.unwrap_or_default())
+            }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -260,4 +401,43 @@ mod tests {
             panic!("'message' is not 'Message::Media'");
         }
     }
+
+    #[test]
+    fn test_der_tool_message() {
+        let json = r#"{"role":"tool","content":"{\"temperature\":21}","tool_call_id":"call_0"}"#;
+        let message = serde_json::from_str::<Message>(json).unwrap();
+        if let Message::Tool(message) = message {
+            assert_eq!(message.tool_call_id(), "call_0");
+        } else {
+            panic!("'message' is not 'Message::Tool'");
+        }
+        let json = r#"{"role":"assistant","content":null,"tool_calls":[{"id":"call_0","type":"function","function":{"name":"get_weather","arguments":"{}"}}]}"#;
+        let message = serde_json::from_str::<Message>(json).unwrap();
+        if let Message::ToolCalls(message) = message {
+            assert_eq!(message.tool_calls()[0].name(), "get_weather");
+        } else {
+            panic!("'message' is not 'Message::ToolCalls'");
+        }
+    }
+
+    #[test]
+    fn test_from_completion() {
+        let completion = Completion {
+            content: Some(String::new()),
+            reasoning_content: Some("Look it up.".to_owned()),
+            tool_calls: Some(vec![ToolCall::new("call_0", "search", "{}")]),
+            ..Default::default()
+        };
+        let Message::ToolCalls(message) = Message::from(completion) else {
+            panic!("'message' is not 'Message::ToolCalls'");
+        };
+        assert_eq!(message.content, None);
+        assert_eq!(message.reasoning_content.as_deref(), Some("Look it up."));
+        let completion = Completion {
+            content: Some("Hi!".to_owned()),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&Message::from(completion)).unwrap();
+        assert_eq!(json, r#"{"role":"assistant","content":"Hi!"}"#);
+    }
 }
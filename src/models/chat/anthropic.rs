@@ -0,0 +1,774 @@
+use std::collections::BTreeMap;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::sse;
+use crate::{
+    completion::FinishReason,
+    error::ApiError,
+    http,
+    message::{Media, Message, Role},
+    options::BorrowedAnthropicModelOptions,
+    provider::Provider,
+    reasoning::{Reasoning, ReasoningBlock},
+    retry,
+    tool::{FunctionCallDelta, ToolCallDelta},
+    usage::PromptTokensDetails,
+    Completion, Error, Prompt, Result, Stream, ToolCall, Usage,
+};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1/messages";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const MIN_BUDGET_TOKENS: u32 = 1024;
+
+async fn api(
+    prompt: &Prompt,
+    options: BorrowedAnthropicModelOptions<'_>,
+    stream: bool,
+) -> Result<reqwest::Response> {
+    let BorrowedAnthropicModelOptions {
+        model,
+        base_url,
+        api_key,
+        max_tokens,
+        tools,
+        reasoning,
+        http,
+        ..
+    } = options;
+    let (system, messages) = messages(prompt)?;
+    let mut body = json!({
+        "model": model,
+        "max_tokens": max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "messages": messages,
+        "stream": stream,
+    });
+    // The thinking budget counts towards `max_tokens`, so the default leaves room for the answer.
+    if let Some(budget_tokens) = reasoning.as_ref().and_then(Reasoning::budget) {
+        let budget_tokens = budget_tokens.max(MIN_BUDGET_TOKENS);
+        body["thinking"] = json!({ "type": "enabled", "budget_tokens": budget_tokens });
+        body["max_tokens"] = json!(max_tokens.unwrap_or(budget_tokens + DEFAULT_MAX_TOKENS));
+    }
+    if let Some(system) = system {
+        body["system"] = json!(system);
+    }
+    if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+        body["tools"] = tools
+            .iter()
+            .map(|tool| {
+                let mut definition = json!({
+                    "name": tool.function.name,
+                    "input_schema": tool.function.parameters,
+                });
+                if let Some(description) = &tool.function.description {
+                    definition["description"] = json!(description);
+                }
+                definition
+            })
+            .collect();
+    }
+    let mut request = http::post(http, base_url.unwrap_or(DEFAULT_BASE_URL))?
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .json(&body);
+    if let Some(api_key) = api_key {
+        request = request.header("x-api-key", api_key);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(Error::api(ApiError::from_response(response).await));
+    }
+    Ok(response)
+}
+
+/// Splits `prompt` into the top-level `system` field and the alternating `messages` of the
+/// Messages API; tool results travel as `tool_result` blocks of a user turn.
+fn messages(prompt: &Prompt) -> Result<(Option<String>, Vec<Value>)> {
+    let mut system = Vec::new();
+    let mut turns: Vec<(Role, Vec<Value>)> = Vec::new();
+    for message in prompt.iter() {
+        let (role, blocks) = match message {
+            Message::Text(message) if message.role == Role::System => {
+                system.push(message.content.clone());
+                continue;
+            }
+            Message::Media(message) if message.role == Role::System => {
+                for media in &message.content {
+                    match media {
+                        Media::Text(text) => system.push(text.clone()),
+                        _ => {
+                            return Err(Error::invalid_request(
+                                "system messages may only contain text",
+                            ))
+                        }
+                    }
+                }
+                continue;
+            }
+            Message::Text(message) => {
+                let mut blocks = thinking(
+                    &message.reasoning_blocks,
+                    &message.reasoning_content,
+                    &message.reasoning_signature,
+                );
+                blocks.push(json!({ "type": "text", "text": message.content }));
+                (message.role, blocks)
+            }
+            Message::Media(message) => (
+                message.role,
+                message.content.iter().map(media).collect::<Result<_>>()?,
+            ),
+            Message::Tool(message) => (
+                Role::User,
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id,
+                    "content": message.content,
+                })],
+            ),
+            Message::ToolCalls(message) => {
+                let mut blocks = thinking(
+                    &message.reasoning_blocks,
+                    &message.reasoning_content,
+                    &message.reasoning_signature,
+                );
+                if let Some(content) = message.content.as_ref().filter(|c| !c.is_empty()) {
+                    blocks.push(json!({ "type": "text", "text": content }));
+                }
+                for tool_call in &message.tool_calls {
+                    let input = match tool_call.arguments().trim() {
+                        "" => json!({}),
+                        arguments => serde_json::from_str::<Value>(arguments)?,
+                    };
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": tool_call.id,
+                        "name": tool_call.name(),
+                        "input": input,
+                    }));
+                }
+                (Role::Assistant, blocks)
+            }
+        };
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role, blocks)),
+        }
+    }
+    let system = if system.is_empty() {
+        None
+    } else {
+        Some(system.join("\n\n"))
+    };
+    let messages = turns
+        .into_iter()
+        .map(|(role, content)| json!({ "role": role, "content": content }))
+        .collect();
+    Ok((system, messages))
+}
+
+/// Thinking is only accepted back as the blocks Anthropic returned, each with its signature, so
+/// reasoning from other providers is dropped.
+fn thinking(
+    reasoning_blocks: &Option<Vec<ReasoningBlock>>,
+    reasoning_content: &Option<String>,
+    reasoning_signature: &Option<String>,
+) -> Vec<Value> {
+    if let Some(reasoning_blocks) = reasoning_blocks
+        .as_ref()
+        .filter(|blocks| !blocks.is_empty())
+    {
+        return reasoning_blocks.iter().map(|block| json!(block)).collect();
+    }
+    match (reasoning_content, reasoning_signature) {
+        (Some(thinking), Some(signature)) if !signature.is_empty() => vec![json!({
+            "type": "thinking",
+            "thinking": thinking,
+            "signature": signature,
+        })],
+        _ => Vec::new(),
+    }
+}
+
+fn media(media: &Media) -> Result<Value> {
+    match media {
+        Media::Text(text) => Ok(json!({ "type": "text", "text": text })),
+        Media::ImageUrl(url) => {
+            let source = match url
+                .strip_prefix("data:")
+                .and_then(|data| data.split_once(";base64,"))
+            {
+                Some((media_type, data)) => {
+                    json!({ "type": "base64", "media_type": media_type, "data": data })
+                }
+                None => json!({ "type": "url", "url": url }),
+            };
+            Ok(json!({ "type": "image", "source": source }))
+        }
+        Media::Video(_) | Media::VideoUrl(_) => {
+            Err(Error::invalid_request("Anthropic does not support video"))
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct Response {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub content: Vec<ContentBlock>,
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    #[serde(default)]
+    pub usage: Option<ResponseUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ContentBlock {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: String,
+    },
+    RedactedThinking {
+        data: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Clone, Copy, Default, Debug)]
+pub(crate) struct ResponseUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub cache_creation_input_tokens: Option<u64>,
+    #[serde(default)]
+    pub cache_read_input_tokens: Option<u64>,
+    #[serde(default)]
+    pub output_tokens: u64,
+}
+
+/// `input_tokens` leaves out the tokens written to and read from the cache; they are counted
+/// into `prompt_tokens` as OpenAI does.
+impl From<ResponseUsage> for Usage {
+    fn from(usage: ResponseUsage) -> Self {
+        let cache_creation_input_tokens = usage.cache_creation_input_tokens.unwrap_or_default();
+        let cached_tokens = usage.cache_read_input_tokens.unwrap_or_default();
+        let prompt_tokens = usage.input_tokens + cache_creation_input_tokens + cached_tokens;
+        Usage {
+            prompt_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: prompt_tokens + usage.output_tokens,
+            prompt_tokens_details: usage.cache_read_input_tokens.map(|cached_tokens| {
+                PromptTokensDetails {
+                    cached_tokens,
+                    ..Default::default()
+                }
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+pub(crate) async fn completion(
+    prompt: &Prompt,
+    options: BorrowedAnthropicModelOptions<'_>,
+) -> Result<Response> {
+    let response = api(prompt, options, false).await?.json().await?;
+    Ok(response)
+}
+
+impl From<Response> for Completion {
+    fn from(response: Response) -> Self {
+        let mut completion = Completion {
+            id: response.id,
+            model: response.model,
+            finish_reason: response.stop_reason.map(finish_reason),
+            usage: response.usage.map(Usage::from),
+            ..Default::default()
+        };
+        for block in response.content {
+            match block {
+                ContentBlock::Text { text } => {
+                    *completion.content.get_or_insert_default() += text.as_str();
+                }
+                ContentBlock::Thinking {
+                    thinking,
+                    signature,
+                } => {
+                    *completion.reasoning_content.get_or_insert_default() += thinking.as_str();
+                    completion.reasoning_signature = Some(signature.clone());
+                    completion
+                        .reasoning_blocks
+                        .get_or_insert_with(Vec::new)
+                        .push(ReasoningBlock::Thinking {
+                            thinking,
+                            signature,
+                        });
+                }
+                ContentBlock::RedactedThinking { data } => {
+                    completion
+                        .reasoning_blocks
+                        .get_or_insert_with(Vec::new)
+                        .push(ReasoningBlock::RedactedThinking { data });
+                }
+                ContentBlock::ToolUse { id, name, input } => {
+                    completion
+                        .tool_calls
+                        .get_or_insert_with(Vec::new)
+                        .push(ToolCall::new(id, name, input.to_string()));
+                }
+                ContentBlock::Other => {}
+            }
+        }
+        completion
+    }
+}
+
+fn finish_reason(stop_reason: String) -> FinishReason {
+    match stop_reason.as_str() {
+        "end_turn" | "stop_sequence" => FinishReason::Stop,
+        "max_tokens" => FinishReason::Length,
+        "tool_use" => FinishReason::ToolCalls,
+        "refusal" => FinishReason::ContentFilter,
+        _ => FinishReason::Other(stop_reason),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Event {
+    MessageStart {
+        message: Response,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: BlockDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        #[serde(default)]
+        delta: MessageDelta,
+        #[serde(default)]
+        usage: Option<ResponseUsage>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Default, Debug)]
+struct MessageDelta {
+    #[serde(default)]
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BlockDelta {
+    TextDelta {
+        text: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    SignatureDelta {
+        signature: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// What a stream keeps between events: the usage so far and the reasoning blocks still being
+/// streamed, by content block index.
+#[derive(Default)]
+struct StreamState {
+    usage: ResponseUsage,
+    blocks: BTreeMap<usize, ReasoningBlock>,
+}
+
+impl Event {
+    fn into_completion(self, state: &mut StreamState) -> Option<Completion> {
+        let usage = &mut state.usage;
+        let tool_call_delta = |index, id, name, arguments| Completion {
+            tool_call_deltas: Some(vec![ToolCallDelta {
+                index,
+                id,
+                function: Some(FunctionCallDelta { name, arguments }),
+            }]),
+            ..Default::default()
+        };
+        match self {
+            Event::MessageStart { message } => {
+                *usage = message.usage.unwrap_or_default();
+                Some(Completion {
+                    id: message.id,
+                    model: message.model,
+                    ..Default::default()
+                })
+            }
+            Event::ContentBlockStart {
+                index,
+                content_block,
+            } => match content_block {
+                ContentBlock::Text { text } if !text.is_empty() => Some(Completion {
+                    content: Some(text),
+                    ..Default::default()
+                }),
+                ContentBlock::Thinking {
+                    thinking,
+                    signature,
+                } => {
+                    state.blocks.insert(
+                        index,
+                        ReasoningBlock::Thinking {
+                            thinking: thinking.clone(),
+                            signature,
+                        },
+                    );
+                    (!thinking.is_empty()).then(|| Completion {
+                        reasoning_content: Some(thinking),
+                        ..Default::default()
+                    })
+                }
+                ContentBlock::RedactedThinking { data } => {
+                    state
+                        .blocks
+                        .insert(index, ReasoningBlock::RedactedThinking { data });
+                    None
+                }
+                ContentBlock::ToolUse { id, name, .. } => {
+                    Some(tool_call_delta(index, Some(id), Some(name), None))
+                }
+                _ => None,
+            },
+            Event::ContentBlockDelta { index, delta } => match delta {
+                BlockDelta::TextDelta { text } => Some(Completion {
+                    content: Some(text),
+                    ..Default::default()
+                }),
+                BlockDelta::ThinkingDelta { thinking } => {
+                    if let Some(ReasoningBlock::Thinking { thinking: text, .. }) =
+                        state.blocks.get_mut(&index)
+                    {
+                        text.push_str(&thinking);
+                    }
+                    Some(Completion {
+                        reasoning_content: Some(thinking),
+                        ..Default::default()
+                    })
+                }
+                BlockDelta::SignatureDelta { signature: delta } => {
+                    if let Some(ReasoningBlock::Thinking { signature, .. }) =
+                        state.blocks.get_mut(&index)
+                    {
+                        signature.push_str(&delta);
+                    }
+                    None
+                }
+                BlockDelta::InputJsonDelta { partial_json } => {
+                    Some(tool_call_delta(index, None, None, Some(partial_json)))
+                }
+                BlockDelta::Other => None,
+            },
+            Event::ContentBlockStop { index } => {
+                let block = state.blocks.remove(&index)?;
+                let reasoning_signature = match &block {
+                    ReasoningBlock::Thinking { signature, .. } => Some(signature.clone()),
+                    ReasoningBlock::RedactedThinking { .. } => None,
+                };
+                Some(Completion {
+                    reasoning_signature,
+                    reasoning_blocks: Some(vec![block]),
+                    ..Default::default()
+                })
+            }
+            Event::MessageDelta {
+                delta,
+                usage: delta_usage,
+            } => {
+                if let Some(delta_usage) = delta_usage {
+                    if delta_usage.input_tokens > 0 {
+                        usage.input_tokens = delta_usage.input_tokens;
+                    }
+                    if delta_usage.cache_creation_input_tokens.is_some() {
+                        usage.cache_creation_input_tokens = delta_usage.cache_creation_input_tokens;
+                    }
+                    if delta_usage.cache_read_input_tokens.is_some() {
+                        usage.cache_read_input_tokens = delta_usage.cache_read_input_tokens;
+                    }
+                    usage.output_tokens = delta_usage.output_tokens;
+                }
+                Some(Completion {
+                    finish_reason: delta.stop_reason.map(finish_reason),
+                    usage: delta_usage.map(|_| (*usage).into()),
+                    ..Default::default()
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+pub(crate) async fn stream(
+    prompt: &Prompt,
+    options: BorrowedAnthropicModelOptions<'_>,
+) -> Result<Stream<Result<Completion>>> {
+    let mut events = sse::events(api(prompt, options, true).await?);
+    let stream = stream! {
+        let mut state = StreamState::default();
+        while let Some(event) = events.next().await {
+            match event.and_then(|event| event.json::<Event>()) {
+                Ok(event) => {
+                    if let Some(completion) = event.into_completion(&mut state) {
+                        yield Ok(completion);
+                    }
+                }
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            }
+        }
+    };
+    Ok(Stream::new(Box::pin(stream)))
+}
+
+#[async_trait]
+impl Provider for BorrowedAnthropicModelOptions<'_> {
+    async fn complete(&self, prompt: &Prompt) -> Result<Completion> {
+        retry::run(self.retry, async || {
+            Ok(completion(prompt, *self).await?.into())
+        })
+        .await
+    }
+
+    async fn stream(&self, prompt: &Prompt) -> Result<Stream<Result<Completion>>> {
+        retry::stream(self.retry, || stream(prompt, *self)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{body_partial_json, header, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{options::AnthropicModelOptions, tool::ToolDefinition};
+
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        let prompt = Prompt::new()
+            .system("Be brief.")
+            .message(
+                Message::media(Role::User)
+                    .image_url("data:image/png;base64,iVBORw0KGgo=")
+                    .text("What is this?")
+                    .into(),
+            )
+            .message(
+                Message::tool_calls(vec![ToolCall::new("toolu_0", "zoom", r#"{"x":1}"#)]).into(),
+            )
+            .tool("toolu_0", "zoomed");
+        let (system, messages) = messages(&prompt).unwrap();
+        assert_eq!(system.as_deref(), Some("Be brief."));
+        assert_eq!(
+            messages,
+            vec![
+                json!({"role": "user", "content": [
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}},
+                    {"type": "text", "text": "What is this?"},
+                ]}),
+                json!({"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "toolu_0", "name": "zoom", "input": {"x": 1}},
+                ]}),
+                json!({"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_0", "content": "zoomed"},
+                ]}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_messages_thinking() {
+        let signed = Completion {
+            content: Some("Hi!".to_owned()),
+            reasoning_content: Some("Simple.".to_owned()),
+            reasoning_signature: Some("sig".to_owned()),
+            ..Default::default()
+        };
+        let unsigned = Message::tool_calls(vec![ToolCall::new("toolu_0", "zoom", "{}")])
+            .reasoning_content("From elsewhere.");
+        let prompt = Prompt::create("Hello")
+            .message(signed.into())
+            .message(unsigned.into());
+        let (_, messages) = messages(&prompt).unwrap();
+        assert_eq!(
+            messages[1],
+            json!({"role": "assistant", "content": [
+                {"type": "thinking", "thinking": "Simple.", "signature": "sig"},
+                {"type": "text", "text": "Hi!"},
+                {"type": "tool_use", "id": "toolu_0", "name": "zoom", "input": {}},
+            ]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_completion() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-api-key", "sk-test"))
+            .and(body_partial_json(json!({
+                "model": "claude-sonnet-4-5",
+                "system": "Be brief.",
+                "stream": false,
+                "max_tokens": 6144,
+                "thinking": {"type": "enabled", "budget_tokens": 2048},
+                "tools": [{"name": "search", "input_schema": {"type": "object", "properties": {}}}],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "msg_0",
+                "type": "message",
+                "role": "assistant",
+                "content": [
+                    {"type": "thinking", "thinking": "Simple.", "signature": "sig0"},
+                    {"type": "redacted_thinking", "data": "opaque"},
+                    {"type": "thinking", "thinking": " Search.", "signature": "sig"},
+                    {"type": "text", "text": "Hi!"},
+                    {"type": "tool_use", "id": "toolu_0", "name": "search", "input": {"q": "hi"}},
+                ],
+                "stop_reason": "tool_use",
+                "usage": {"input_tokens": 12, "output_tokens": 6},
+            })))
+            .mount(&server)
+            .await;
+        let options = AnthropicModelOptions::new()
+            .base_url(server.uri())
+            .model("claude-sonnet-4-5")
+            .api_key("sk-test")
+            .reasoning(Reasoning::new().budget_tokens(2048))
+            .tool(ToolDefinition::new("search"));
+        let prompt = Prompt::new().system("Be brief.").user("Hello");
+        let completion: Completion = completion(&prompt, options.borrow()).await.unwrap().into();
+        assert_eq!(completion.content.as_deref(), Some("Hi!"));
+        assert_eq!(
+            completion.reasoning_content.as_deref(),
+            Some("Simple. Search.")
+        );
+        assert_eq!(completion.reasoning_signature.as_deref(), Some("sig"));
+        assert_eq!(completion.id.as_deref(), Some("msg_0"));
+        assert_eq!(completion.finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(
+            completion.tool_calls.clone().unwrap(),
+            vec![ToolCall::new("toolu_0", "search", r#"{"q":"hi"}"#)]
+        );
+        assert_eq!(completion.usage.as_ref().unwrap().total_tokens, 18);
+        let body = server.received_requests().await.unwrap()[0]
+            .body_json::<Value>()
+            .unwrap();
+        assert!(body["tools"][0].get("description").is_none());
+
+        let prompt = Prompt::create("Hello").message(completion.into());
+        let (_, messages) = messages(&prompt).unwrap();
+        assert_eq!(
+            messages[1]["content"],
+            json!([
+                {"type": "thinking", "thinking": "Simple.", "signature": "sig0"},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "thinking", "thinking": " Search.", "signature": "sig"},
+                {"type": "text", "text": "Hi!"},
+                {"type": "tool_use", "id": "toolu_0", "name": "search", "input": {"q": "hi"}},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg_0","type":"message","role":"assistant","content":[],"usage":{"input_tokens":12,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Sim"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"ple."}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"sig"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"redacted_thinking","data":"opaque"}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"content_block_start","index":2,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":2,"delta":{"type":"text_delta","text":"Hi!"}}"#,
+            r#"{"type":"content_block_start","index":3,"content_block":{"type":"tool_use","id":"toolu_0","name":"search","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":3,"delta":{"type":"input_json_delta","partial_json":"{\"q\":"}}"#,
+            r#"{"type":"content_block_delta","index":3,"delta":{"type":"input_json_delta","partial_json":"\"hi\"}"}}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":6}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+        let body = events
+            .iter()
+            .map(|event| {
+                let ty = serde_json::from_str::<Value>(event).unwrap()["type"].clone();
+                format!("event: {}\ndata: {}\n\n", ty.as_str().unwrap(), event)
+            })
+            .collect::<String>();
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+        let options = AnthropicModelOptions::new().base_url(server.uri());
+        let completion = stream(&Prompt::create("Hello"), options.borrow())
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(completion.id.as_deref(), Some("msg_0"));
+        assert_eq!(completion.finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(completion.content.as_deref(), Some("Hi!"));
+        assert_eq!(completion.reasoning_content.as_deref(), Some("Simple."));
+        assert_eq!(completion.reasoning_signature.as_deref(), Some("sig"));
+        assert_eq!(
+            completion.reasoning_blocks.unwrap(),
+            vec![
+                ReasoningBlock::Thinking {
+                    thinking: "Simple.".to_owned(),
+                    signature: "sig".to_owned(),
+                },
+                ReasoningBlock::RedactedThinking {
+                    data: "opaque".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(
+            completion.tool_calls.unwrap(),
+            vec![ToolCall::new("toolu_0", "search", r#"{"q":"hi"}"#)]
+        );
+        assert_eq!(completion.usage.unwrap().total_tokens, 18);
+    }
+}
@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::sse;
+use crate::{
+    completion::FinishReason,
+    error::ApiError,
+    http,
+    message::{Media, Message, Role},
+    options::BorrowedGeminiModelOptions,
+    provider::Provider,
+    retry,
+    usage::{CompletionTokensDetails, PromptTokensDetails},
+    Completion, Error, Prompt, Result, Stream, ToolCall, Usage,
+};
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+
+async fn api(
+    prompt: &Prompt,
+    options: BorrowedGeminiModelOptions<'_>,
+    stream: bool,
+) -> Result<reqwest::Response> {
+    let BorrowedGeminiModelOptions {
+        model,
+        base_url,
+        api_key,
+        tools,
+        reasoning,
+        http,
+        ..
+    } = options;
+    let Some(model) = model else {
+        return Err(Error::invalid_request("'model' is required"));
+    };
+    let (system_instruction, contents) = contents(prompt)?;
+    let mut body = json!({ "contents": contents });
+    if let Some(system_instruction) = system_instruction {
+        body["systemInstruction"] = system_instruction;
+    }
+    if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+        // `parameters` only takes Gemini's OpenAPI subset; generated schemas are full JSON Schema.
+        let function_declarations = tools
+            .iter()
+            .map(|tool| {
+                let mut declaration = json!({
+                    "name": tool.function.name,
+                    "parametersJsonSchema": tool.function.parameters,
+                });
+                if let Some(description) = &tool.function.description {
+                    declaration["description"] = json!(description);
+                }
+                declaration
+            })
+            .collect::<Vec<_>>();
+        body["tools"] = json!([{ "functionDeclarations": function_declarations }]);
+    }
+    if let Some(reasoning) = reasoning {
+        let mut thinking_config = json!({});
+        if let Some(budget_tokens) = reasoning.budget() {
+            thinking_config["thinkingBudget"] = json!(budget_tokens);
+        }
+        if let Some(include_thoughts) = reasoning.include_thoughts {
+            thinking_config["includeThoughts"] = json!(include_thoughts);
+        }
+        body["generationConfig"] = json!({ "thinkingConfig": thinking_config });
+    }
+    let base_url = base_url.unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/');
+    let url = if stream {
+        format!("{base_url}/models/{model}:streamGenerateContent?alt=sse")
+    } else {
+        format!("{base_url}/models/{model}:generateContent")
+    };
+    let mut request = http::post(http, &url)?.json(&body);
+    if let Some(api_key) = api_key {
+        request = request.header("x-goog-api-key", api_key);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(Error::api(ApiError::from_response(response).await));
+    }
+    Ok(response)
+}
+
+/// Converts `prompt` into `systemInstruction` and `contents`, merging consecutive turns of the
+/// same role so that parallel function responses share a single turn.
+fn contents(prompt: &Prompt) -> Result<(Option<Value>, Vec<Value>)> {
+    let mut system = Vec::new();
+    let mut tool_names = HashMap::new();
+    let mut turns: Vec<(&str, Vec<Value>)> = Vec::new();
+    for message in prompt.iter() {
+        let (role, parts) = match message {
+            Message::Text(message) if message.role == Role::System => {
+                system.push(json!({ "text": message.content }));
+                continue;
+            }
+            Message::Media(message) if message.role == Role::System => {
+                system.extend(parts(&message.content)?);
+                continue;
+            }
+            Message::Text(message) => {
+                (role(message.role), vec![json!({ "text": message.content })])
+            }
+            Message::Media(message) => (role(message.role), parts(&message.content)?),
+            Message::Tool(message) => {
+                let Some(name) = tool_names.get(message.tool_call_id.as_str()).copied() else {
+                    return Err(Error::invalid_request(format!(
+                        "no tool call with id '{}' precedes its result",
+                        message.tool_call_id
+                    )));
+                };
+                let response = match serde_json::from_str::<Value>(&message.content) {
+                    Ok(response @ Value::Object(_)) => response,
+                    _ => json!({ "content": message.content }),
+                };
+                (
+                    "user",
+                    vec![json!({ "functionResponse": { "name": name, "response": response } })],
+                )
+            }
+            Message::ToolCalls(message) => {
+                let mut parts = Vec::new();
+                if let Some(content) = message.content.as_ref().filter(|c| !c.is_empty()) {
+                    parts.push(json!({ "text": content }));
+                }
+                // Gemini signs the first function call of a turn; Anthropic's signatures come with
+                // their reasoning blocks and are not valid here.
+                let mut thought_signature = message
+                    .reasoning_signature
+                    .as_ref()
+                    .filter(|_| message.reasoning_blocks.is_none());
+                for tool_call in &message.tool_calls {
+                    tool_names.insert(tool_call.id.as_str(), tool_call.name());
+                    let args = match tool_call.arguments().trim() {
+                        "" => json!({}),
+                        arguments => serde_json::from_str::<Value>(arguments)?,
+                    };
+                    let mut part =
+                        json!({ "functionCall": { "name": tool_call.name(), "args": args } });
+                    if let Some(thought_signature) = thought_signature.take() {
+                        part["thoughtSignature"] = json!(thought_signature);
+                    }
+                    parts.push(part);
+                }
+                ("model", parts)
+            }
+        };
+        match turns.last_mut() {
+            Some((last_role, last_parts)) if *last_role == role => last_parts.extend(parts),
+            _ => turns.push((role, parts)),
+        }
+    }
+    let system_instruction = if system.is_empty() {
+        None
+    } else {
+        Some(json!({ "parts": system }))
+    };
+    let contents = turns
+        .into_iter()
+        .map(|(role, parts)| json!({ "role": role, "parts": parts }))
+        .collect();
+    Ok((system_instruction, contents))
+}
+
+fn role(role: Role) -> &'static str {
+    match role {
+        Role::Assistant => "model",
+        _ => "user",
+    }
+}
+
+fn parts(content: &[Media]) -> Result<Vec<Value>> {
+    let mut parts = Vec::new();
+    for media in content {
+        match media {
+            Media::Text(text) => parts.push(json!({ "text": text })),
+            Media::ImageUrl(url) => parts.push(part(url, "image/jpeg")),
+            Media::VideoUrl(url) => parts.push(part(url, "video/mp4")),
+            Media::Video(urls) => parts.extend(urls.iter().map(|url| part(url, "image/jpeg"))),
+        }
+    }
+    Ok(parts)
+}
+
+/// `data:` URLs are sent as `inlineData`, everything else as `fileData` with a MIME type guessed
+/// from the extension.
+fn part(url: &str, default_mime_type: &str) -> Value {
+    if let Some((mime_type, data)) = url
+        .strip_prefix("data:")
+        .and_then(|data| data.split_once(";base64,"))
+    {
+        return json!({ "inlineData": { "mimeType": mime_type, "data": data } });
+    }
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let extension = path.rsplit_once('.').map(|(_, extension)| extension);
+    let mime_type = match extension.map(str::to_ascii_lowercase).as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("heic") => "image/heic",
+        Some("mp4") => "video/mp4",
+        Some("mov") => "video/quicktime",
+        Some("webm") => "video/webm",
+        Some("mpeg" | "mpg") => "video/mpeg",
+        _ => default_mime_type,
+    };
+    json!({ "fileData": { "mimeType": mime_type, "fileUri": url } })
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Response {
+    #[serde(default)]
+    pub response_id: Option<String>,
+    #[serde(default)]
+    pub model_version: Option<String>,
+    #[serde(default)]
+    pub candidates: Vec<Candidate>,
+    #[serde(default)]
+    pub usage_metadata: Option<UsageMetadata>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Candidate {
+    #[serde(default)]
+    pub content: Option<Content>,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct Content {
+    #[serde(default)]
+    pub parts: Vec<Part>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Part {
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub thought: bool,
+    #[serde(default)]
+    pub function_call: Option<FunctionCall>,
+    #[serde(default)]
+    pub thought_signature: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct FunctionCall {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageMetadata {
+    #[serde(default)]
+    pub prompt_token_count: u64,
+    #[serde(default)]
+    pub cached_content_token_count: Option<u64>,
+    #[serde(default)]
+    pub candidates_token_count: u64,
+    #[serde(default)]
+    pub thoughts_token_count: Option<u64>,
+    #[serde(default)]
+    pub total_token_count: u64,
+}
+
+impl From<UsageMetadata> for Usage {
+    fn from(usage: UsageMetadata) -> Self {
+        let thoughts_token_count = usage.thoughts_token_count.unwrap_or_default();
+        Usage {
+            prompt_tokens: usage.prompt_token_count,
+            completion_tokens: usage.candidates_token_count + thoughts_token_count,
+            total_tokens: usage.total_token_count,
+            prompt_tokens_details: usage.cached_content_token_count.map(|cached_tokens| {
+                PromptTokensDetails {
+                    cached_tokens,
+                    ..Default::default()
+                }
+            }),
+            completion_tokens_details: usage.thoughts_token_count.map(|reasoning_tokens| {
+                CompletionTokensDetails {
+                    reasoning_tokens,
+                    ..Default::default()
+                }
+            }),
+        }
+    }
+}
+
+fn finish_reason(finish_reason: String) -> FinishReason {
+    match finish_reason.as_str() {
+        "STOP" => FinishReason::Stop,
+        "MAX_TOKENS" => FinishReason::Length,
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "IMAGE_SAFETY" => {
+            FinishReason::ContentFilter
+        }
+        _ => FinishReason::Other(finish_reason),
+    }
+}
+
+impl Response {
+    fn into_completion(self, tool_calls_seen: &mut usize) -> Completion {
+        let mut completion = Completion {
+            id: self.response_id,
+            model: self.model_version,
+            usage: self
+                .usage_metadata
+                .filter(|usage| usage.total_token_count > 0)
+                .map(Usage::from),
+            ..Default::default()
+        };
+        let Some(candidate) = self.candidates.into_iter().next() else {
+            return completion;
+        };
+        let parts = candidate
+            .content
+            .map(|content| content.parts)
+            .unwrap_or_default();
+        for part in parts {
+            if let Some(thought_signature) = part.thought_signature {
+                completion.reasoning_signature = Some(thought_signature);
+            }
+            if let Some(text) = part.text.filter(|text| !text.is_empty()) {
+                let target = if part.thought {
+                    &mut completion.reasoning_content
+                } else {
+                    &mut completion.content
+                };
+                *target.get_or_insert_default() += text.as_str();
+            }
+            if let Some(function_call) = part.function_call {
+                let id = function_call
+                    .id
+                    .unwrap_or_else(|| format!("call_{}", *tool_calls_seen));
+                *tool_calls_seen += 1;
+                let arguments = match function_call.args {
+                    Value::Null => "{}".to_owned(),
+                    args => args.to_string(),
+                };
+                completion
+                    .tool_calls
+                    .get_or_insert_with(Vec::new)
+                    .push(ToolCall::new(id, function_call.name, arguments));
+            }
+        }
+        completion.finish_reason = candidate
+            .finish_reason
+            .map(|reason| finish_reason(reason).with_tool_calls(completion.tool_calls.is_some()));
+        completion
+    }
+}
+
+pub(crate) async fn completion(
+    prompt: &Prompt,
+    options: BorrowedGeminiModelOptions<'_>,
+) -> Result<Response> {
+    let response = api(prompt, options, false).await?.json().await?;
+    Ok(response)
+}
+
+impl From<Response> for Completion {
+    fn from(response: Response) -> Self {
+        response.into_completion(&mut 0)
+    }
+}
+
+pub(crate) async fn stream(
+    prompt: &Prompt,
+    options: BorrowedGeminiModelOptions<'_>,
+) -> Result<Stream<Result<Completion>>> {
+    let mut events = sse::events(api(prompt, options, true).await?);
+    let stream = stream! {
+        let mut tool_calls_seen = 0;
+        while let Some(event) = events.next().await {
+            match event.and_then(|event| event.json::<Response>()) {
+                Ok(response) => yield Ok(response.into_completion(&mut tool_calls_seen)),
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            }
+        }
+    };
+    Ok(Stream::new(Box::pin(stream)))
+}
+
+#[async_trait]
+impl Provider for BorrowedGeminiModelOptions<'_> {
+    async fn complete(&self, prompt: &Prompt) -> Result<Completion> {
+        retry::run(self.retry, async || {
+            Ok(completion(prompt, *self).await?.into())
+        })
+        .await
+    }
+
+    async fn stream(&self, prompt: &Prompt) -> Result<Stream<Result<Completion>>> {
+        retry::stream(self.retry, || stream(prompt, *self)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{options::GeminiModelOptions, reasoning::ReasoningEffort, Reasoning};
+
+    use super::*;
+
+    #[test]
+    fn test_contents() {
+        let prompt = Prompt::new()
+            .system("Describe precisely.")
+            .message(
+                Message::media(Role::User)
+                    .image_url("https://example.com/cat.PNG?size=large")
+                    .video_url("https://example.com/clip.mov")
+                    .image_url("data:image/webp;base64,UklGRg==")
+                    .text("What happens?")
+                    .into(),
+            )
+            .message(
+                Message::tool_calls(vec![ToolCall::new("call_0", "zoom", r#"{"x":1}"#)]).into(),
+            )
+            .tool("call_0", "zoomed");
+        let (system_instruction, contents) = contents(&prompt).unwrap();
+        assert_eq!(
+            system_instruction,
+            Some(json!({"parts": [{"text": "Describe precisely."}]}))
+        );
+        assert_eq!(
+            contents,
+            vec![
+                json!({"role": "user", "parts": [
+                    {"fileData": {"mimeType": "image/png", "fileUri": "https://example.com/cat.PNG?size=large"}},
+                    {"fileData": {"mimeType": "video/quicktime", "fileUri": "https://example.com/clip.mov"}},
+                    {"inlineData": {"mimeType": "image/webp", "data": "UklGRg=="}},
+                    {"text": "What happens?"},
+                ]}),
+                json!({"role": "model", "parts": [{"functionCall": {"name": "zoom", "args": {"x": 1}}}]}),
+                json!({"role": "user", "parts": [
+                    {"functionResponse": {"name": "zoom", "response": {"content": "zoomed"}}},
+                ]}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contents_unknown_tool_call() {
+        let prompt = Prompt::create("Zoom in.").tool("call_9", "zoomed");
+        let Err(Error::InvalidRequest(message)) = contents(&prompt) else {
+            panic!("expected an invalid request");
+        };
+        assert!(message.contains("call_9"));
+    }
+
+    #[tokio::test]
+    async fn test_completion() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:generateContent"))
+            .and(header("x-goog-api-key", "test"))
+            .and(body_partial_json(json!({
+                "systemInstruction": {"parts": [{"text": "Be brief."}]},
+                "generationConfig": {"thinkingConfig": {"thinkingBudget": 1024, "includeThoughts": true}},
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{
+                    "content": {"role": "model", "parts": [
+                        {"text": "The user greets me.", "thought": true},
+                        {"text": "Hello!"},
+                    ]},
+                    "finishReason": "STOP",
+                }],
+                "usageMetadata": {
+                    "promptTokenCount": 8,
+                    "candidatesTokenCount": 2,
+                    "thoughtsTokenCount": 5,
+                    "totalTokenCount": 15,
+                },
+            })))
+            .mount(&server)
+            .await;
+        let options = GeminiModelOptions::new()
+            .base_url(server.uri())
+            .model("gemini-2.5-flash")
+            .api_key("test")
+            .reasoning(
+                Reasoning::new()
+                    .effort(ReasoningEffort::Low)
+                    .include_thoughts(true),
+            );
+        let prompt = Prompt::new().system("Be brief.").user("Hi");
+        let completion: Completion = completion(&prompt, options.borrow()).await.unwrap().into();
+        assert_eq!(completion.content.as_deref(), Some("Hello!"));
+        assert_eq!(
+            completion.reasoning_content.as_deref(),
+            Some("The user greets me.")
+        );
+        assert_eq!(completion.finish_reason, Some(FinishReason::Stop));
+        let usage = completion.usage.unwrap();
+        assert_eq!((usage.completion_tokens, usage.total_tokens), (7, 15));
+    }
+
+    #[tokio::test]
+    async fn test_tools_json_schema() {
+        #[allow(dead_code)]
+        #[derive(schemars::JsonSchema)]
+        struct Zoom {
+            level: Option<u32>,
+        }
+
+        let parameters = crate::tool::schema_for::<Zoom>();
+        assert_eq!(parameters["properties"]["level"]["format"], "uint32");
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({
+                "tools": [{"functionDeclarations": [
+                    {"name": "zoom", "parametersJsonSchema": parameters},
+                ]}],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{"content": {"role": "model", "parts": [{"text": "ok"}]}}],
+            })))
+            .mount(&server)
+            .await;
+        let options = GeminiModelOptions::new()
+            .base_url(server.uri())
+            .model("gemini-2.5-flash")
+            .tools(vec![
+                crate::ToolDefinition::new("zoom").parameters(parameters)
+            ]);
+        completion(&Prompt::create("Zoom in."), options.borrow())
+            .await
+            .unwrap();
+        let body = server.received_requests().await.unwrap()[0]
+            .body_json::<Value>()
+            .unwrap();
+        let declaration = &body["tools"][0]["functionDeclarations"][0];
+        assert!(declaration.get("parameters").is_none());
+        assert!(declaration.get("description").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let chunks = [
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Thinking","thought":true}]}}],"usageMetadata":{"promptTokenCount":8,"totalTokenCount":8}}"#,
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"functionCall":{"name":"search","args":{"q":"rust"}},"thoughtSignature":"sig"}]}}],"usageMetadata":{"promptTokenCount":8,"candidatesTokenCount":4,"totalTokenCount":12}}"#,
+        ];
+        let body = chunks
+            .iter()
+            .map(|chunk| format!("data: {chunk}\r\n\r\n"))
+            .collect::<String>();
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:streamGenerateContent"))
+            .and(query_param("alt", "sse"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+        let options = GeminiModelOptions::new()
+            .base_url(server.uri())
+            .model("gemini-2.5-flash");
+        let completion = stream(&Prompt::create("rust?"), options.borrow())
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(completion.reasoning_content.as_deref(), Some("Thinking"));
+        assert_eq!(completion.reasoning_signature.as_deref(), Some("sig"));
+        assert_eq!(
+            completion.tool_calls.clone().unwrap(),
+            vec![ToolCall::new("call_0", "search", r#"{"q":"rust"}"#)]
+        );
+        assert_eq!(completion.usage.as_ref().unwrap().total_tokens, 12);
+
+        let prompt = Prompt::create("rust?").message(completion.into());
+        let (_, contents) = contents(&prompt).unwrap();
+        assert_eq!(
+            contents[1],
+            json!({"role": "model", "parts": [
+                {"functionCall": {"name": "search", "args": {"q": "rust"}}, "thoughtSignature": "sig"},
+            ]})
+        );
+    }
+}
@@ -1,37 +1,84 @@
-use anyhow::Ok;
 use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::json;
 
-use crate::{options::BorrowedModelOptions, Completion, Model, ModelOptions, Prompt, Stream};
+use crate::{
+    message::{Message, Role},
+    options::BorrowedModelOptions,
+    response_format::{json_content, ResponseFormat},
+    Completion, Error, Model, ModelOptions, Prompt, Result, Stream,
+};
 
+mod anthropic;
+mod gemini;
+mod ollama;
 mod openai;
+mod sse;
+mod think;
 
 #[async_trait]
 pub trait ChatModel: Model {
-    async fn completion(
-        &self,
-        prompt: &Prompt,
-        options: ModelOptions,
-    ) -> anyhow::Result<Completion> {
+    async fn completion(&self, prompt: &Prompt, options: ModelOptions) -> Result<Completion> {
         let options = self.options().merge(&options);
-        match options {
-            BorrowedModelOptions::OpenAI(options) => {
-                Ok(openai::completion(prompt, options).await?.into())
-            }
-            _ => panic!("Unsupported type of 'ModelOptions'"),
-        }
+        check_context_window(self, prompt, &options)?;
+        options.provider()?.complete(prompt).await
+    }
+
+    async fn text_completion(&self, prompt: &Prompt, options: ModelOptions) -> Result<String> {
+        Ok(self.completion(prompt, options).await?.to_string())
+    }
+
+    /// Asks for a reply matching the JSON schema of `T` and parses it.
+    async fn completion_as<T>(&self, prompt: &Prompt, options: ModelOptions) -> Result<T>
+    where
+        Self: Sized,
+        T: DeserializeOwned + JsonSchema + Send,
+    {
+        self.completion_as_with_reprompts(prompt, options, 0).await
     }
 
-    async fn text_completion(
+    /// Like [`completion_as`](Self::completion_as), but a reply that fails to parse is sent back
+    /// to the model together with the error, up to `reprompts` times.
+    async fn completion_as_with_reprompts<T>(
         &self,
         prompt: &Prompt,
         options: ModelOptions,
-    ) -> anyhow::Result<String> {
-        let options = self.options().merge(&options);
-        match options {
-            BorrowedModelOptions::OpenAI(options) => {
-                Ok(openai::completion(prompt, options).await?.to_string())
+        reprompts: usize,
+    ) -> Result<T>
+    where
+        Self: Sized,
+        T: DeserializeOwned + JsonSchema + Send,
+    {
+        let format = ResponseFormat::json_schema_for::<T>();
+        let mut prompt = prompt.clone();
+        if !options.supports_response_format(self.options()) {
+            if let Some(instruction) = format.instruction() {
+                prompt.insert(0, Message::text(Role::System, instruction));
+            }
+        }
+        let options = options.with_response_format(self.options(), format);
+        let mut reprompt = 0;
+        loop {
+            let content = self
+                .completion(&prompt, options.clone())
+                .await?
+                .content
+                .unwrap_or_default();
+            let err = match serde_json::from_str::<T>(json_content(&content)) {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            if reprompt == reprompts {
+                return Err(Error::decode(format!(
+                    "reply does not match the expected schema: {err}: {content}"
+                )));
             }
-            _ => panic!("Unsupported type of 'ModelOptions'"),
+            reprompt += 1;
+            prompt = prompt.assistant(&content).user(format!(
+                "The reply above does not match the expected JSON schema: {err}. \
+                 Reply again with only the corrected JSON."
+            ));
         }
     }
 }
@@ -42,53 +89,57 @@ pub trait StreamingChatModel: Model {
         &self,
         prompt: &Prompt,
         options: ModelOptions,
-    ) -> anyhow::Result<Stream<Completion>> {
+    ) -> Result<Stream<Result<Completion>>> {
         let options = self.options().merge(&options);
-        match options {
-            BorrowedModelOptions::OpenAI(options) => {
-                Ok(openai::stream(prompt, options).await?.into())
-            }
-            _ => panic!("Unsupported type of 'ModelOptions'"),
-        }
+        check_context_window(self, prompt, &options)?;
+        options.provider()?.stream(prompt).await
     }
 
     async fn text_stream(
         &self,
         prompt: &Prompt,
         options: ModelOptions,
-    ) -> anyhow::Result<Stream<String>> {
-        let options = self.options().merge(&options);
-        match options {
-            BorrowedModelOptions::OpenAI(options) => {
-                Ok(openai::stream(prompt, options).await?.into())
-            }
-            _ => panic!("Unsupported type of 'ModelOptions'"),
-        }
+    ) -> Result<Stream<Result<String>>> {
+        Ok(self.stream(prompt, options).await?.into())
     }
 
-    async fn completion(
-        &self,
-        prompt: &Prompt,
-        options: ModelOptions,
-    ) -> anyhow::Result<Completion> {
-        let options = self.options().merge(&options);
-        match options {
-            BorrowedModelOptions::OpenAI(options) => {
-                Ok(openai::stream(prompt, options).await?.collect().await)
-            }
-            _ => panic!("Unsupported type of 'ModelOptions'"),
-        }
+    async fn completion(&self, prompt: &Prompt, options: ModelOptions) -> Result<Completion> {
+        self.stream(prompt, options).await?.collect().await
     }
 
-    async fn text_completion(
-        &self,
-        prompt: &Prompt,
-        options: ModelOptions,
-    ) -> anyhow::Result<String> {
-        Ok(self.text_stream(prompt, options).await?.collect().await)
+    async fn text_completion(&self, prompt: &Prompt, options: ModelOptions) -> Result<String> {
+        self.text_stream(prompt, options).await?.collect().await
     }
 }
 
+fn check_context_window<M: Model + ?Sized>(
+    model: &M,
+    prompt: &Prompt,
+    options: &BorrowedModelOptions,
+) -> Result<()> {
+    let Some(tokenizer) = model.tokenizer() else {
+        return Ok(());
+    };
+    let Some(context_window) = options.model().and_then(|name| model.context_window(name)) else {
+        return Ok(());
+    };
+    // Tool definitions and the response schema are sent along with the prompt.
+    let tokens = prompt.estimate_tokens(tokenizer)
+        + options
+            .tools()
+            .map_or(0, |tools| tokenizer.count(&json!(tools).to_string()))
+        + options
+            .response_format()
+            .map_or(0, |format| tokenizer.count(&json!(format).to_string()));
+    if tokens > context_window {
+        return Err(Error::ContextWindowExceeded {
+            tokens,
+            context_window,
+        });
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -141,4 +192,217 @@ mod tests {
             .unwrap();
         println!("{completion:?}");
     }
+
+    struct Echo;
+
+    #[async_trait]
+    impl crate::Provider for Echo {
+        async fn complete(&self, prompt: &Prompt) -> Result<Completion> {
+            Ok(Completion {
+                content: Some(serde_json::to_string(prompt)?),
+                ..Default::default()
+            })
+        }
+
+        async fn stream(&self, prompt: &Prompt) -> Result<Stream<Result<Completion>>> {
+            Ok(futures::stream::iter([self.complete(prompt).await]).into())
+        }
+    }
+
+    struct Custom {
+        options: ModelOptions,
+    }
+
+    impl Model for Custom {
+        fn options(&self) -> &ModelOptions {
+            &self.options
+        }
+    }
+
+    impl ChatModel for Custom {}
+
+    #[tokio::test]
+    async fn test_custom_provider() {
+        let model = Custom {
+            options: ModelOptions::custom(Echo),
+        };
+        let text = model
+            .text_completion(&Prompt::create("hi"), ModelOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(text, r#"[{"role":"user","content":"hi"}]"#);
+    }
+
+    #[tokio::test]
+    async fn test_missing_provider() {
+        let model = Custom {
+            options: ModelOptions::default(),
+        };
+        let result = model
+            .completion(&Prompt::create("hi"), ModelOptions::default())
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, PartialEq, Debug)]
+    struct City {
+        name: String,
+        population: u64,
+    }
+
+    #[tokio::test]
+    async fn test_completion_as_reprompts() {
+        use serde_json::{json, Value};
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let reply = |content: &str| {
+            ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": content}}],
+            }))
+        };
+        let server = MockServer::start().await;
+        let mount = async || {
+            server.reset().await;
+            Mock::given(method("POST"))
+                .respond_with(reply(r#"{"name":"杭州"}"#))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .respond_with(reply(
+                    "```json\n{\"name\":\"杭州\",\"population\":12000000}\n```",
+                ))
+                .mount(&server)
+                .await;
+        };
+        let model = Custom {
+            options: OpenAIModelOptions::new().base_url(server.uri()).into(),
+        };
+        let prompt = Prompt::create("杭州");
+        mount().await;
+        let err = model
+            .completion_as::<City>(&prompt, ModelOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Decode(_)));
+        mount().await;
+        let city = model
+            .completion_as_with_reprompts::<City>(&prompt, ModelOptions::default(), 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            city,
+            City {
+                name: "杭州".to_owned(),
+                population: 12000000,
+            }
+        );
+        let requests = server.received_requests().await.unwrap();
+        let bodies = requests
+            .iter()
+            .map(|request| request.body_json::<Value>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(bodies[0]["response_format"]["type"], "json_schema");
+        assert_eq!(
+            bodies[0]["response_format"]["json_schema"]["schema"]["required"],
+            json!(["name", "population"])
+        );
+        assert_eq!(bodies[1]["messages"].as_array().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_completion_as_schema_in_prompt() {
+        use serde_json::{json, Value};
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "message": {"role": "assistant", "content": r#"{"name":"杭州","population":12000000}"#},
+                "done": true,
+            })))
+            .mount(&server)
+            .await;
+        let model = Custom {
+            options: crate::OllamaModelOptions::new()
+                .base_url(server.uri())
+                .into(),
+        };
+        let city = model
+            .completion_as::<City>(&Prompt::create("杭州"), ModelOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(city.population, 12000000);
+        let body = server.received_requests().await.unwrap()[0]
+            .body_json::<Value>()
+            .unwrap();
+        assert_eq!(body["messages"][0]["role"], "system");
+        let instruction = body["messages"][0]["content"].as_str().unwrap();
+        assert!(instruction.contains(r#""required":["name","population"]"#));
+        assert_eq!(body["messages"][1]["content"], "杭州");
+    }
+
+    struct Tokenized {
+        options: ModelOptions,
+    }
+
+    impl Model for Tokenized {
+        fn options(&self) -> &ModelOptions {
+            &self.options
+        }
+
+        fn tokenizer(&self) -> Option<&dyn crate::Tokenizer> {
+            Some(&crate::Approximate)
+        }
+    }
+
+    impl ChatModel for Tokenized {}
+
+    #[tokio::test]
+    async fn test_context_window_exceeded() {
+        use serde_json::json;
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": "ok"}}],
+            })))
+            .mount(&server)
+            .await;
+        let model = Tokenized {
+            options: OpenAIModelOptions::new()
+                .model("gpt-4-0613")
+                .base_url(server.uri())
+                .into(),
+        };
+        let prompt = Prompt::create("a".repeat(40_000));
+        let err = model
+            .completion(&prompt, ModelOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ContextWindowExceeded {
+                tokens: 10_006,
+                context_window: 8_192,
+            }
+        ));
+        assert!(server.received_requests().await.unwrap().is_empty());
+        let larger = OpenAIModelOptions::new().model("gpt-4o").into();
+        assert!(model.completion(&prompt, larger).await.is_ok());
+        let tools = (0..10)
+            .map(|i| crate::ToolDefinition::new(format!("tool_{i}")).description("d".repeat(4_000)))
+            .collect();
+        let prompt = Prompt::create("a".repeat(20_000));
+        assert!(model
+            .completion(&prompt, ModelOptions::default())
+            .await
+            .is_ok());
+        let err = model
+            .completion(&prompt, OpenAIModelOptions::new().tools(tools).into())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ContextWindowExceeded { tokens, .. } if tokens > 15_000));
+    }
 }
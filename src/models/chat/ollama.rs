@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    completion::FinishReason,
+    error::ApiError,
+    http::{self, HttpOptions},
+    message::{Media, Message},
+    options::BorrowedOllamaModelOptions,
+    provider::Provider,
+    retry, Completion, Error, Prompt, Result, Stream, ToolCall, Usage,
+};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434/api/chat";
+
+async fn api(
+    prompt: &Prompt,
+    options: BorrowedOllamaModelOptions<'_>,
+    stream: bool,
+) -> Result<reqwest::Response> {
+    let BorrowedOllamaModelOptions {
+        model,
+        base_url,
+        api_key,
+        tools,
+        reasoning,
+        http,
+        ..
+    } = options;
+    let mut body = json!({
+        "model": model,
+        "messages": messages(prompt, http).await?,
+        "stream": stream,
+    });
+    if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+        body["tools"] = json!(tools);
+    }
+    // `think` takes an effort level for models such as gpt-oss and a switch for the others.
+    if let Some(reasoning) = reasoning {
+        body["think"] = match (reasoning.effort, reasoning.include_thoughts) {
+            (Some(effort), _) => json!(effort.as_str()),
+            (None, Some(include_thoughts)) => json!(include_thoughts),
+            (None, None) => json!(true),
+        };
+    }
+    let mut request = http::post(http, base_url.unwrap_or(DEFAULT_BASE_URL))?.json(&body);
+    if let Some(api_key) = api_key {
+        request = request.bearer_auth(api_key);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(Error::api(ApiError::from_response(response).await));
+    }
+    Ok(response)
+}
+
+async fn messages(prompt: &Prompt, http: Option<&HttpOptions>) -> Result<Vec<Value>> {
+    let mut tool_names = HashMap::new();
+    let mut messages = Vec::new();
+    for message in prompt.iter() {
+        let message = match message {
+            Message::Text(message) => {
+                let mut value = json!({ "role": message.role, "content": message.content });
+                if let Some(thinking) = &message.reasoning_content {
+                    value["thinking"] = json!(thinking);
+                }
+                value
+            }
+            Message::Media(message) => {
+                let mut texts = Vec::new();
+                let mut images = Vec::new();
+                for media in &message.content {
+                    match media {
+                        Media::Text(text) => texts.push(text.as_str()),
+                        Media::ImageUrl(url) => images.push(image(url, http).await?),
+                        Media::Video(_) | Media::VideoUrl(_) => {
+                            return Err(Error::invalid_request("Ollama does not support video"))
+                        }
+                    }
+                }
+                json!({ "role": message.role, "content": texts.join("\n"), "images": images })
+            }
+            Message::Tool(message) => {
+                let mut value = json!({ "role": message.role, "content": message.content });
+                if let Some(name) = tool_names.get(message.tool_call_id.as_str()) {
+                    value["tool_name"] = json!(name);
+                }
+                value
+            }
+            Message::ToolCalls(message) => {
+                let mut tool_calls = Vec::new();
+                for tool_call in &message.tool_calls {
+                    tool_names.insert(tool_call.id.as_str(), tool_call.name());
+                    let arguments = match tool_call.arguments().trim() {
+                        "" => json!({}),
+                        arguments => serde_json::from_str::<Value>(arguments)?,
+                    };
+                    tool_calls.push(json!({
+                        "function": { "name": tool_call.name(), "arguments": arguments },
+                    }));
+                }
+                let mut value = json!({
+                    "role": message.role,
+                    "content": message.content.as_deref().unwrap_or_default(),
+                    "tool_calls": tool_calls,
+                });
+                if let Some(thinking) = &message.reasoning_content {
+                    value["thinking"] = json!(thinking);
+                }
+                value
+            }
+        };
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+/// Ollama only accepts raw base64 image data, so `data:` URLs are unwrapped, remote URLs are
+/// downloaded with the configured client and anything else is passed through as already encoded.
+async fn image(url: &str, http: Option<&HttpOptions>) -> Result<String> {
+    if let Some((_, data)) = url
+        .strip_prefix("data:")
+        .and_then(|data| data.split_once(";base64,"))
+    {
+        return Ok(data.to_owned());
+    }
+    if url.starts_with("http://") || url.starts_with("https://") {
+        let response = http::get(http, url)?.send().await?.error_for_status()?;
+        return Ok(STANDARD.encode(response.bytes().await?));
+    }
+    Ok(url.to_owned())
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct Response {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub message: Option<ResponseMessage>,
+    #[serde(default)]
+    pub done_reason: Option<String>,
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ResponseMessage {
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub thinking: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ResponseToolCall>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ResponseToolCall {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub function: ResponseFunction,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct ResponseFunction {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+impl Response {
+    /// Ollama does not always assign tool call ids, so missing ones are numbered by how many tool
+    /// calls have been seen so far in the response.
+    fn into_completion(self, tool_calls_seen: &mut usize) -> Completion {
+        let usage = match (self.prompt_eval_count, self.eval_count) {
+            (None, None) => None,
+            (prompt_tokens, completion_tokens) => {
+                let prompt_tokens = prompt_tokens.unwrap_or_default();
+                let completion_tokens = completion_tokens.unwrap_or_default();
+                Some(Usage {
+                    prompt_tokens,
+                    completion_tokens,
+                    total_tokens: prompt_tokens + completion_tokens,
+                    ..Default::default()
+                })
+            }
+        };
+        let finish_reason = self.done_reason.map(|reason| match reason.as_str() {
+            "stop" => FinishReason::Stop,
+            "length" => FinishReason::Length,
+            _ => FinishReason::Other(reason),
+        });
+        let Some(message) = self.message else {
+            return Completion {
+                model: self.model,
+                finish_reason,
+                usage,
+                ..Default::default()
+            };
+        };
+        let tool_calls = message
+            .tool_calls
+            .filter(|t| !t.is_empty())
+            .map(|tool_calls| {
+                tool_calls
+                    .into_iter()
+                    .map(|tool_call| {
+                        let id = tool_call
+                            .id
+                            .unwrap_or_else(|| format!("call_{}", *tool_calls_seen));
+                        *tool_calls_seen += 1;
+                        let arguments = match tool_call.function.arguments {
+                            Value::Null => "{}".to_owned(),
+                            Value::String(arguments) => arguments,
+                            arguments => arguments.to_string(),
+                        };
+                        ToolCall::new(id, tool_call.function.name, arguments)
+                    })
+                    .collect()
+            });
+        Completion {
+            model: self.model,
+            content: message.content.filter(|s| !s.is_empty()),
+            reasoning_content: message.thinking.filter(|s| !s.is_empty()),
+            finish_reason: finish_reason.map(|reason| reason.with_tool_calls(tool_calls.is_some())),
+            tool_calls,
+            usage,
+            ..Default::default()
+        }
+    }
+}
+
+pub(crate) async fn completion(
+    prompt: &Prompt,
+    options: BorrowedOllamaModelOptions<'_>,
+) -> Result<Response> {
+    let response: Response = api(prompt, options, false).await?.json().await?;
+    if let Some(error) = response.error {
+        return Err(Error::api(ApiError::from_value(&error.into())));
+    }
+    Ok(response)
+}
+
+impl From<Response> for Completion {
+    fn from(response: Response) -> Self {
+        response.into_completion(&mut 0)
+    }
+}
+
+fn parse(line: &[u8]) -> Result<Response> {
+    let response = serde_json::from_slice::<Response>(line)
+        .map_err(|err| Error::decode(format!("'{}': {err}", String::from_utf8_lossy(line))))?;
+    if let Some(error) = response.error {
+        return Err(Error::api(ApiError::from_value(&error.into())));
+    }
+    Ok(response)
+}
+
+pub(crate) async fn stream(
+    prompt: &Prompt,
+    options: BorrowedOllamaModelOptions<'_>,
+) -> Result<Stream<Result<Completion>>> {
+    let mut response = api(prompt, options, true).await?;
+    let stream = stream! {
+        let mut buffer = Vec::new();
+        let mut tool_calls_seen = 0;
+        loop {
+            let chunk = match response.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(err) => {
+                    yield Err(err.into());
+                    return;
+                }
+            };
+            buffer.extend_from_slice(&chunk);
+            while let Some(position) = buffer.iter().position(|b| *b == b'\n') {
+                let line = buffer.drain(..=position).collect::<Vec<_>>();
+                if line.trim_ascii().is_empty() {
+                    continue;
+                }
+                match parse(&line) {
+                    Ok(response) => yield Ok(response.into_completion(&mut tool_calls_seen)),
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                }
+            }
+        }
+        if !buffer.trim_ascii().is_empty() {
+            yield parse(&buffer).map(|response| response.into_completion(&mut tool_calls_seen));
+        }
+    };
+    Ok(Stream::new(Box::pin(stream)))
+}
+
+#[async_trait]
+impl Provider for BorrowedOllamaModelOptions<'_> {
+    async fn complete(&self, prompt: &Prompt) -> Result<Completion> {
+        retry::run(self.retry, async || {
+            Ok(completion(prompt, *self).await?.into())
+        })
+        .await
+    }
+
+    async fn stream(&self, prompt: &Prompt) -> Result<Stream<Result<Completion>>> {
+        retry::stream(self.retry, || stream(prompt, *self)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{message::Role, options::OllamaModelOptions, Reasoning, ReasoningEffort};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_messages() {
+        let prompt = Prompt::new()
+            .message(
+                Message::media(Role::User)
+                    .image_url("data:image/png;base64,iVBORw0KGgo=")
+                    .text("What is this?")
+                    .into(),
+            )
+            .message(
+                Message::tool_calls(vec![ToolCall::new("call_0", "zoom", r#"{"x":1}"#)]).into(),
+            )
+            .tool("call_0", "zoomed");
+        assert_eq!(
+            messages(&prompt, None).await.unwrap(),
+            vec![
+                json!({"role": "user", "content": "What is this?", "images": ["iVBORw0KGgo="]}),
+                json!({"role": "assistant", "content": "", "tool_calls": [
+                    {"function": {"name": "zoom", "arguments": {"x": 1}}},
+                ]}),
+                json!({"role": "tool", "content": "zoomed", "tool_name": "zoom"}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remote_image() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/cat.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"\x89PNG".to_vec()))
+            .mount(&server)
+            .await;
+        let prompt = Prompt::new().message(
+            Message::media(Role::User)
+                .image_url(format!("{}/cat.png", server.uri()))
+                .into(),
+        );
+        let http = HttpOptions::new().header("authorization", "Bearer secret");
+        let sent = messages(&prompt, Some(&http)).await.unwrap();
+        assert_eq!(sent[0]["images"], json!(["iVBORw=="]));
+        let request = &server.received_requests().await.unwrap()[0];
+        assert!(!request.headers.contains_key("authorization"));
+
+        let prompt = Prompt::new().message(
+            Message::media(Role::User)
+                .image_url(format!("{}/missing.png", server.uri()))
+                .into(),
+        );
+        let err = messages(&prompt, None).await.unwrap_err();
+        assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let lines = [
+            r#"{"model":"qwen3","created_at":"2025-01-01T00:00:00Z","message":{"role":"assistant","content":"","thinking":"用户"},"done":false}"#,
+            r#"{"model":"qwen3","created_at":"2025-01-01T00:00:00Z","message":{"role":"assistant","content":"","thinking":"打招呼"},"done":false}"#,
+            r#"{"model":"qwen3","created_at":"2025-01-01T00:00:00Z","message":{"role":"assistant","content":"你好"},"done":false}"#,
+            r#"{"model":"qwen3","created_at":"2025-01-01T00:00:00Z","message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":9,"eval_count":4}"#,
+        ];
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                json!({ "model": "qwen3", "stream": true }),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(lines.join("\n") + "\n", "application/x-ndjson"),
+            )
+            .mount(&server)
+            .await;
+        let options = OllamaModelOptions::new()
+            .base_url(server.uri())
+            .model("qwen3");
+        let completion = stream(&Prompt::create("你好"), options.borrow())
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(completion.content.as_deref(), Some("你好"));
+        assert_eq!(completion.reasoning_content.as_deref(), Some("用户打招呼"));
+        let usage = completion.usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens), (9, 4));
+    }
+
+    #[tokio::test]
+    async fn test_completion_tool_calls() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "qwen3",
+                "message": {"role": "assistant", "content": "", "tool_calls": [
+                    {"function": {"name": "search", "arguments": {"q": "rust"}}},
+                ]},
+                "done": true,
+                "done_reason": "stop",
+                "prompt_eval_count": 20,
+                "eval_count": 10,
+            })))
+            .mount(&server)
+            .await;
+        let options = OllamaModelOptions::new().base_url(server.uri());
+        let completion: Completion = completion(&Prompt::create("rust?"), options.borrow())
+            .await
+            .unwrap()
+            .into();
+        assert_eq!(
+            completion.tool_calls.unwrap(),
+            vec![ToolCall::new("call_0", "search", r#"{"q":"rust"}"#)]
+        );
+        assert_eq!(completion.model.as_deref(), Some("qwen3"));
+        assert_eq!(completion.finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(completion.usage.unwrap().total_tokens, 30);
+    }
+
+    #[tokio::test]
+    async fn test_think() {
+        let cases = [
+            (
+                Reasoning::new().effort(ReasoningEffort::High),
+                json!("high"),
+            ),
+            (
+                Reasoning::new()
+                    .effort(ReasoningEffort::Low)
+                    .include_thoughts(true),
+                json!("low"),
+            ),
+            (Reasoning::new().budget_tokens(2048), json!(true)),
+            (Reasoning::new().include_thoughts(false), json!(false)),
+        ];
+        for (reasoning, think) in cases {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(body_partial_json(json!({ "think": think })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "message": {"role": "assistant", "content": "Hi"},
+                    "done": true,
+                })))
+                .mount(&server)
+                .await;
+            let options = OllamaModelOptions::new()
+                .base_url(server.uri())
+                .reasoning(reasoning);
+            completion(&Prompt::create("Hi"), options.borrow())
+                .await
+                .unwrap();
+        }
+    }
+}
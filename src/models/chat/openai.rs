@@ -1,103 +1,205 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
-use anyhow::anyhow;
 use async_stream::stream;
+use async_trait::async_trait;
 use futures::StreamExt;
 use serde::Deserialize;
-use serde_json::json;
+use serde_json::{json, Map, Value};
 
-use crate::{options::BorrowedOpenAIModelOptions, Completion, Prompt, Stream, Usage};
+use super::{
+    sse,
+    think::{Split, ThinkTags},
+};
+use crate::{
+    completion::{self, CompletionAccumulator, FinishReason, TokenLogprob},
+    error::ApiError,
+    http,
+    options::BorrowedOpenAIModelOptions,
+    provider::Provider,
+    retry,
+    tool::ToolCallDelta,
+    Completion, Error, Prompt, Result, Stream, ToolCall, Usage,
+};
 
 async fn api(
     prompt: &Prompt,
     options: BorrowedOpenAIModelOptions<'_>,
     stream: bool,
-) -> anyhow::Result<reqwest::Response> {
+) -> Result<reqwest::Response> {
     let BorrowedOpenAIModelOptions {
         model,
         base_url,
         api_key,
+        tools,
+        temperature,
+        top_p,
+        max_tokens,
+        max_completion_tokens,
+        stop,
+        seed,
+        presence_penalty,
+        frequency_penalty,
+        logit_bias,
+        n,
+        user,
+        logprobs,
+        top_logprobs,
+        extra_body,
+        response_format,
+        reasoning,
+        send_reasoning,
+        thinking_params,
+        http,
+        ..
     } = options;
     if base_url.is_none() {
-        return Err(anyhow!("'base_url' is required"));
+        return Err(Error::invalid_request("'base_url' is required"));
     }
-    let client = reqwest::Client::new();
-    let mut request = client.post(base_url.unwrap()).json(&json!({
+    let mut messages = json!(prompt);
+    for message in messages.as_array_mut().into_iter().flatten() {
+        if let Value::Object(message) = message {
+            message.remove("reasoning_signature");
+            message.remove("reasoning_blocks");
+            if send_reasoning != Some(true) {
+                message.remove("reasoning_content");
+            }
+        }
+    }
+    let mut body = json!({
         "model": model,
-        "messages": prompt,
+        "messages": messages,
         "stream": stream,
         "stream_options": {
             "include_usage": true
         }
-    }));
+    });
+    if let Some(tools) = tools.filter(|tools| !tools.is_empty()) {
+        body["tools"] = json!(tools);
+    }
+    let parameters = json!({
+        "temperature": temperature,
+        "top_p": top_p,
+        "max_tokens": max_tokens,
+        "max_completion_tokens": max_completion_tokens,
+        "stop": stop,
+        "seed": seed,
+        "presence_penalty": presence_penalty,
+        "frequency_penalty": frequency_penalty,
+        "logit_bias": logit_bias,
+        "n": n,
+        "user": user,
+        "logprobs": logprobs,
+        "top_logprobs": top_logprobs,
+        "response_format": response_format,
+    });
+    if let Some(reasoning) = reasoning {
+        if let Some(effort) = reasoning.effort_level() {
+            body["reasoning_effort"] = json!(effort.as_str());
+        }
+        if thinking_params == Some(true) {
+            if let Some(budget_tokens) = reasoning.budget_tokens {
+                body["enable_thinking"] = json!(true);
+                body["thinking_budget"] = json!(budget_tokens);
+            }
+            if let Some(include_thoughts) = reasoning.include_thoughts {
+                body["enable_thinking"] = json!(include_thoughts);
+            }
+        }
+    }
+    if let Value::Object(parameters) = parameters {
+        for (key, value) in parameters.into_iter().filter(|(_, value)| !value.is_null()) {
+            body[key] = value;
+        }
+    }
+    for extra_body in extra_body.into_iter().flatten() {
+        merge(&mut body, extra_body);
+    }
+    let mut request = http::post(http, base_url.unwrap())?.json(&body);
     if let Some(api_key) = api_key {
         request = request.bearer_auth(api_key);
     }
     let response = request.send().await?;
     if !response.status().is_success() {
-        return Err(anyhow!(
-            "request failed with status code {}: {}",
-            response.status(),
-            response.text().await.unwrap_or_default()
-        ));
+        return Err(Error::api(ApiError::from_response(response).await));
     }
     Ok(response)
 }
 
-#[derive(Deserialize, Debug)]
+/// Deep-merges `source` into `target`: objects are merged key by key, anything else replaces.
+fn merge(target: &mut Value, source: &Map<String, Value>) {
+    for (key, value) in source {
+        match (target.get_mut(key), value) {
+            (Some(target @ Value::Object(_)), Value::Object(value)) => merge(target, value),
+            _ => target[key] = value.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
 pub(crate) struct Response {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub created: Option<u64>,
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
     pub choices: Vec<Choice>,
     #[serde(default)]
     pub usage: Option<Usage>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Default, Debug)]
 pub(crate) struct Choice {
+    #[serde(default)]
+    pub index: u32,
     #[serde(default)]
     pub message: Option<Content>,
     #[serde(default)]
-    pub delta: Option<Content>,
+    pub delta: Option<Content<ToolCallDelta>>,
+    #[serde(default)]
+    pub logprobs: Option<Logprobs>,
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
 }
 
-#[derive(Deserialize, Debug)]
-pub(crate) struct Content {
+#[derive(Deserialize, Default, Debug)]
+pub(crate) struct Logprobs {
+    #[serde(default)]
+    pub content: Option<Vec<TokenLogprob>>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub(crate) struct Content<T = ToolCall> {
     #[serde(default)]
     pub content: Option<String>,
     #[serde(default)]
     pub reasoning_content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<T>>,
 }
 
 impl Response {
+    /// The choice with index 0, which the top-level fields of a [`Completion`] describe.
+    pub(crate) fn first(&self) -> Option<&Choice> {
+        self.choices.iter().find(|choice| choice.index == 0)
+    }
+
     pub(crate) fn content(&self) -> Option<&String> {
-        if let Some(Choice { message, delta }) = self.choices.first() {
-            if let Some(content) = message {
-                if let Some(content) = content.content() {
-                    return Some(content);
-                }
-            }
-            if let Some(content) = delta {
-                if let Some(content) = content.content() {
-                    return Some(content);
-                }
-            }
-        }
-        None
+        self.first().and_then(Choice::content)
     }
 
     pub(crate) fn reasoning_content(&self) -> Option<&String> {
-        if let Some(Choice { message, delta }) = self.choices.first() {
-            if let Some(content) = message {
-                if let Some(reasoning_content) = content.reasoning_content() {
-                    return Some(reasoning_content);
-                }
-            }
-            if let Some(content) = delta {
-                if let Some(reasoning_content) = content.reasoning_content() {
-                    return Some(reasoning_content);
-                }
-            }
-        }
-        None
+        self.first().and_then(Choice::reasoning_content)
+    }
+
+    pub(crate) fn tool_calls(&self) -> Option<&Vec<ToolCall>> {
+        self.message().and_then(|content| content.tool_calls())
+    }
+
+    pub(crate) fn tool_call_deltas(&self) -> Option<&Vec<ToolCallDelta>> {
+        self.delta().and_then(|content| content.tool_calls())
     }
 
     pub(crate) fn usage(&self) -> Option<&Usage> {
@@ -105,26 +207,79 @@ impl Response {
     }
 
     pub(crate) fn message(&self) -> Option<&Content> {
-        self.choices
-            .first()
-            .and_then(|choice| choice.message.as_ref())
+        self.first().and_then(|choice| choice.message.as_ref())
     }
 
-    pub(crate) fn delta(&self) -> Option<&Content> {
-        self.choices
-            .first()
-            .and_then(|choice| choice.delta.as_ref())
+    pub(crate) fn delta(&self) -> Option<&Content<ToolCallDelta>> {
+        self.first().and_then(|choice| choice.delta.as_ref())
     }
 
-    pub(crate) fn into_delta(self) -> Option<Content> {
+    /// Each choice keeps its own splitter, since the deltas of several choices interleave.
+    fn split_think_tags(&mut self, tags: &mut BTreeMap<u32, ThinkTags>) {
+        for choice in &mut self.choices {
+            if let Some(delta) = &mut choice.delta {
+                delta.split_think_tags(tags.entry(choice.index).or_default());
+            }
+        }
+    }
+
+    pub(crate) fn into_delta(self) -> Option<Content<ToolCallDelta>> {
         self.choices
             .into_iter()
-            .next()
+            .find(|choice| choice.index == 0)
             .and_then(|choice| choice.delta)
     }
 }
 
-impl Content {
+impl Choice {
+    pub(crate) fn logprobs(&self) -> Option<Vec<TokenLogprob>> {
+        self.logprobs
+            .as_ref()
+            .and_then(|logprobs| logprobs.content.clone())
+            .filter(|content| !content.is_empty())
+    }
+
+    pub(crate) fn content(&self) -> Option<&String> {
+        let message = self.message.as_ref().and_then(|content| content.content());
+        message.or_else(|| self.delta.as_ref().and_then(|content| content.content()))
+    }
+
+    pub(crate) fn reasoning_content(&self) -> Option<&String> {
+        let message = self
+            .message
+            .as_ref()
+            .and_then(|content| content.reasoning_content());
+        message.or_else(|| {
+            self.delta
+                .as_ref()
+                .and_then(|content| content.reasoning_content())
+        })
+    }
+}
+
+impl From<&Choice> for completion::Choice {
+    fn from(choice: &Choice) -> Self {
+        completion::Choice {
+            index: choice.index,
+            content: choice.content().cloned(),
+            reasoning_content: choice.reasoning_content().cloned(),
+            tool_calls: choice
+                .message
+                .as_ref()
+                .and_then(|content| content.tool_calls())
+                .cloned(),
+            tool_call_deltas: choice
+                .delta
+                .as_ref()
+                .and_then(|content| content.tool_calls())
+                .cloned(),
+            logprobs: choice.logprobs(),
+            finish_reason: choice.finish_reason.clone(),
+        }
+    }
+}
+
+impl<T> Content<T> {
     pub(crate) fn content(&self) -> Option<&String> {
         if let Some(content) = &self.content {
             if !content.is_empty() {
@@ -142,22 +297,79 @@ impl Content {
         }
         None
     }
+
+    pub(crate) fn tool_calls(&self) -> Option<&Vec<T>> {
+        self.tool_calls
+            .as_ref()
+            .filter(|tool_calls| !tool_calls.is_empty())
+    }
+
+    fn split_think_tags(&mut self, tags: &mut ThinkTags) {
+        if let Some(content) = self.content.take() {
+            self.extend(tags.push(&content));
+        }
+    }
+
+    fn extend(&mut self, split: Split) {
+        if !split.reasoning_content.is_empty() {
+            *self.reasoning_content.get_or_insert_default() += split.reasoning_content.as_str();
+        }
+        if !split.content.is_empty() {
+            *self.content.get_or_insert_default() += split.content.as_str();
+        }
+    }
 }
 
 pub(crate) async fn completion<'a>(
     prompt: &Prompt,
     options: BorrowedOpenAIModelOptions<'a>,
-) -> anyhow::Result<Response> {
-    let response = api(prompt, options, false).await?.json().await?;
+) -> Result<Response> {
+    let mut response: Response = api(prompt, options, false).await?.json().await?;
+    if options.think_tags == Some(true) {
+        for message in response
+            .choices
+            .iter_mut()
+            .filter_map(|choice| choice.message.as_mut())
+        {
+            let mut tags = ThinkTags::new();
+            message.split_think_tags(&mut tags);
+            message.extend(tags.finish());
+        }
+    }
     Ok(response)
 }
 
 impl From<Response> for Completion {
     fn from(response: Response) -> Self {
+        // Streamed chunks may carry only the deltas of other choices, so those are listed
+        // whenever any choice besides the first is present.
+        let choices = response
+            .choices
+            .iter()
+            .any(|choice| choice.index > 0)
+            .then(|| {
+                response
+                    .choices
+                    .iter()
+                    .map(completion::Choice::from)
+                    .collect()
+            });
         Completion {
+            finish_reason: response
+                .first()
+                .and_then(|choice| choice.finish_reason.clone()),
+            logprobs: response.first().and_then(Choice::logprobs),
+            choices,
             content: response.content().cloned(),
             reasoning_content: response.reasoning_content().cloned(),
+            tool_calls: response.tool_calls().cloned(),
+            tool_call_deltas: response.tool_call_deltas().cloned(),
             usage: response.usage().cloned(),
+            id: response.id,
+            model: response.model,
+            created: response.created,
+            system_fingerprint: response.system_fingerprint,
+            ..Default::default()
         }
     }
 }
@@ -182,50 +394,81 @@ impl Display for Response {
 pub(crate) async fn stream<'a>(
     prompt: &Prompt,
     options: BorrowedOpenAIModelOptions<'a>,
-) -> anyhow::Result<Stream<Response>> {
-    let mut response = api(prompt, options, true).await?;
+) -> Result<Stream<Result<Response>>> {
+    let mut events = sse::events(api(prompt, options, true).await?);
+    let mut think_tags = (options.think_tags == Some(true)).then(BTreeMap::new);
     let stream = stream! {
-        'outer: while let Ok(Some(chunk)) = response.chunk().await {
-            let text = String::from_utf8_lossy(&chunk).to_string();
-            for line in text.split("\n\n") {
-                if let Some(data) = line.strip_prefix("data: ") {
-                    if let Ok(response) = serde_json::from_str(data) {
-                        yield response;
-                    } else {
-                        break 'outer;
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(event) if event.is_done() => break,
+                Ok(event) => match event.json::<Response>() {
+                    Ok(mut response) => {
+                        if let Some(tags) = &mut think_tags {
+                            response.split_think_tags(tags);
+                        }
+                        yield Ok(response)
                     }
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                },
+                Err(err) => {
+                    yield Err(err);
+                    return;
                 }
             }
         }
+        let mut choices = Vec::new();
+        for (index, mut tags) in think_tags.into_iter().flatten() {
+            let mut delta = Content::default();
+            delta.extend(tags.finish());
+            if delta.content.is_some() || delta.reasoning_content.is_some() {
+                choices.push(Choice { index, delta: Some(delta), ..Default::default() });
+            }
+        }
+        if !choices.is_empty() {
+            yield Ok(Response { choices, ..Default::default() });
+        }
     };
     Ok(Stream::new(Box::pin(stream)))
 }
 
-impl From<Stream<Response>> for Stream<Completion> {
-    fn from(stream: Stream<Response>) -> Self {
-        stream.into_inner().map(|response| response.into()).into()
+impl From<Stream<Result<Response>>> for Stream<Result<Completion>> {
+    fn from(stream: Stream<Result<Response>>) -> Self {
+        stream
+            .into_inner()
+            .map(|response| response.map(Completion::from))
+            .into()
     }
 }
 
-impl From<Stream<Response>> for Stream<String> {
-    fn from(mut stream: Stream<Response>) -> Self {
+impl From<Stream<Result<Response>>> for Stream<Result<String>> {
+    fn from(mut stream: Stream<Result<Response>>) -> Self {
         let text_stream = stream! {
             let mut reasoning = false;
             while let Some(item) = stream.next().await {
+                let item = match item {
+                    Ok(item) => item,
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                };
                 if let Some(content) = item.into_delta() {
                     if let Some(reasoning_content) = content.reasoning_content() {
                         if !reasoning {
-                            yield "<think>".to_string();
+                            yield Ok("<think>".to_string());
                             reasoning = true;
                         }
-                        yield reasoning_content.clone();
+                        yield Ok(reasoning_content.clone());
                     }
                     if let Some(content) = content.content() {
                         if reasoning {
-                            yield "</think>".to_string();
+                            yield Ok("</think>".to_string());
                             reasoning = false;
                         }
-                        yield content.clone();
+                        yield Ok(content.clone());
                     }
                 }
             }
@@ -234,35 +477,41 @@ impl From<Stream<Response>> for Stream<String> {
     }
 }
 
-impl Stream<Response> {
-    pub async fn collect(mut self) -> Completion {
-        let mut content_completed = None;
-        let mut reasoning_content_completed = None;
-        let mut usage_completed = None;
+impl Stream<Result<Response>> {
+    pub async fn collect(mut self) -> Result<Completion> {
+        let mut accumulator = CompletionAccumulator::new();
         while let Some(item) = self.next().await {
-            if let Some(content) = item.delta() {
-                if let Some(content) = content.content() {
-                    *content_completed.get_or_insert_default() += content.as_str();
-                }
-                if let Some(reasoning_content) = content.reasoning_content() {
-                    *reasoning_content_completed.get_or_insert_default() +=
-                        reasoning_content.as_str();
-                }
-            }
-            if let Some(usage) = item.usage() {
-                usage_completed = Some(usage.clone());
-            }
+            accumulator.push(item?.into());
         }
-        Completion::new(
-            content_completed,
-            reasoning_content_completed,
-            usage_completed,
-        )
+        accumulator.finish()
+    }
+}
+
+#[async_trait]
+impl Provider for BorrowedOpenAIModelOptions<'_> {
+    async fn complete(&self, prompt: &Prompt) -> Result<Completion> {
+        retry::run(self.retry, async || {
+            Ok(completion(prompt, *self).await?.into())
+        })
+        .await
+    }
+
+    async fn stream(&self, prompt: &Prompt) -> Result<Stream<Result<Completion>>> {
+        retry::stream(self.retry, async || Ok(stream(prompt, *self).await?.into())).await
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use wiremock::{
+        matchers::{body_partial_json, header, method},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{
+        options::OpenAIModelOptions, HttpOptions, Reasoning, ReasoningEffort, RetryPolicy,
+    };
+
     use super::*;
 
     #[test]
@@ -271,4 +520,390 @@ mod tests {
         let response = serde_json::from_str::<Response>(json).unwrap();
         println!("{response:?}");
     }
+
+    #[test]
+    fn test_der_tool_calls() {
+        let json = r#"{"choices":[{"finish_reason":"tool_calls","index":0,"message":{"role":"assistant","content":"","tool_calls":[{"id":"call_0","type":"function","function":{"name":"get_weather","arguments":"{\"city\":\"Hangzhou\"}"}}]}}],"usage":{"prompt_tokens":10,"completion_tokens":5,"total_tokens":15}}"#;
+        let completion = Completion::from(serde_json::from_str::<Response>(json).unwrap());
+        let tool_calls = completion.tool_calls.unwrap();
+        assert_eq!(tool_calls[0].id, "call_0");
+        assert_eq!(tool_calls[0].name(), "get_weather");
+        assert_eq!(tool_calls[0].arguments(), r#"{"city":"Hangzhou"}"#);
+        assert_eq!(completion.finish_reason, Some(FinishReason::ToolCalls));
+    }
+
+    #[tokio::test]
+    async fn test_collect_tool_call_deltas() {
+        let chunks = [
+            r#"{"id":"chatcmpl-0","model":"gpt-4o","created":1762759403,"choices":[{"index":0,"delta":{"role":"assistant","content":null,"tool_calls":[{"index":0,"id":"call_0","type":"function","function":{"name":"search","arguments":""}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"query\""}}]}}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":":\"rust\"}"}}]}}]}"#,
+            r#"{"id":"chatcmpl-0","choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+        ];
+        let responses = chunks.map(|chunk| Ok(serde_json::from_str::<Response>(chunk).unwrap()));
+        let stream: Stream<Result<Completion>> =
+            Stream::from(futures::stream::iter(responses)).into();
+        let completion = stream.collect().await.unwrap();
+        assert_eq!(
+            completion.tool_calls.unwrap(),
+            vec![ToolCall::new("call_0", "search", r#"{"query":"rust"}"#)]
+        );
+        assert_eq!(completion.id.as_deref(), Some("chatcmpl-0"));
+        assert_eq!(completion.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(completion.created, Some(1762759403));
+        assert_eq!(completion.finish_reason, Some(FinishReason::ToolCalls));
+    }
+
+    #[tokio::test]
+    async fn test_collect_choices() {
+        let chunks = [
+            r#"{"choices":[{"index":0,"delta":{"content":"Hel"}},{"index":1,"delta":{"content":"Hi"}}]}"#,
+            r#"{"choices":[{"index":1,"delta":{"content":"!"},"finish_reason":"stop"}]}"#,
+            r#"{"choices":[{"index":0,"delta":{"content":"lo"},"finish_reason":"length"}]}"#,
+        ];
+        let responses = chunks.map(|chunk| Ok(serde_json::from_str::<Response>(chunk).unwrap()));
+        let stream: Stream<Result<Response>> = Stream::from(futures::stream::iter(responses));
+        let completion = stream.collect().await.unwrap();
+        assert_eq!(completion.content.as_deref(), Some("Hello"));
+        let choices = completion.choices.unwrap();
+        assert_eq!(choices.len(), 2);
+        assert_eq!(choices[0].content.as_deref(), Some("Hello"));
+        assert_eq!(choices[0].finish_reason, Some(FinishReason::Length));
+        assert_eq!(choices[1].index, 1);
+        assert_eq!(choices[1].content.as_deref(), Some("Hi!"));
+        assert_eq!(choices[1].finish_reason, Some(FinishReason::Stop));
+
+        let json = r#"{"choices":[{"index":0,"message":{"content":"A"}},{"index":1,"message":{"content":"B"}}]}"#;
+        let completion = Completion::from(serde_json::from_str::<Response>(json).unwrap());
+        assert_eq!(completion.content.as_deref(), Some("A"));
+        let choices = completion.choices.unwrap();
+        assert_eq!(choices[1].content.as_deref(), Some("B"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_event() {
+        let body = concat!(
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"你好\"}}]}\n\n",
+            "data: {\"error\":{\"message\":\"upstream overloaded\",\"type\":\"server_error\"}}\n\n",
+        );
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+        let options = OpenAIModelOptions::new().base_url(server.uri());
+        let mut stream: Stream<Result<String>> = stream(&Prompt::create("你好"), options.borrow())
+            .await
+            .unwrap()
+            .into();
+        assert_eq!(stream.next().await.unwrap().unwrap(), "你好");
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Api(ApiError { status: None, kind: Some(kind), .. }) if kind == "server_error"
+        ));
+        assert!(stream.next().await.is_none());
+        let result = super::stream(&Prompt::create("你好"), options.borrow())
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_think_tags() {
+        let deltas = ["<thi", "nk>Greeting.</th", "ink>\n\nHel", "lo <"];
+        let body = deltas
+            .iter()
+            .map(|delta| {
+                let chunk = json!({"choices": [{"index": 0, "delta": {"content": delta}}]});
+                format!("data: {chunk}\n\n")
+            })
+            .collect::<String>()
+            + "data: [DONE]\n\n";
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"index": 0, "message": {"content": "<think>Greeting.</think>\nHello"}}],
+            })))
+            .mount(&server)
+            .await;
+        let options = OpenAIModelOptions::new()
+            .base_url(server.uri())
+            .think_tags(true);
+        let completion = stream(&Prompt::create("Hi"), options.borrow())
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        assert_eq!(completion.reasoning_content.as_deref(), Some("Greeting."));
+        assert_eq!(completion.content.as_deref(), Some("Hello <"));
+        let completion: Completion = super::completion(&Prompt::create("Hi"), options.borrow())
+            .await
+            .unwrap()
+            .into();
+        assert_eq!(completion.reasoning_content.as_deref(), Some("Greeting."));
+        assert_eq!(completion.content.as_deref(), Some("Hello"));
+        let options = OpenAIModelOptions::new().base_url(server.uri());
+        let completion: Completion = super::completion(&Prompt::create("Hi"), options.borrow())
+            .await
+            .unwrap()
+            .into();
+        assert_eq!(completion.reasoning_content, None);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "7")
+                    .set_body_json(json!({"error": {
+                        "message": "Rate limit reached for requests",
+                        "type": "requests",
+                        "code": "rate_limit_exceeded",
+                    }})),
+            )
+            .mount(&server)
+            .await;
+        let options = OpenAIModelOptions::new().base_url(server.uri());
+        let err = completion(&Prompt::create("你好"), options.borrow())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::RateLimit(_)));
+        assert_eq!(err.status(), Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(err.retry_after(), Some(std::time::Duration::from_secs(7)));
+        assert_eq!(
+            err.api_error().unwrap().code.as_deref(),
+            Some("rate_limit_exceeded")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_rate_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": "你好"}}],
+            })))
+            .mount(&server)
+            .await;
+        let options = OpenAIModelOptions::new()
+            .base_url(server.uri())
+            .retry(RetryPolicy::new().max_attempts(3));
+        let completion = options
+            .borrow()
+            .complete(&Prompt::create("你好"))
+            .await
+            .unwrap();
+        assert_eq!(completion.content.as_deref(), Some("你好"));
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_http_options() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("x-trace", "1"))
+            .and(header("user-agent", "agentx-test"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": "你好"}}],
+            })))
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::builder()
+            .user_agent("agentx-test")
+            .build()
+            .unwrap();
+        let options = OpenAIModelOptions::new()
+            .base_url(server.uri())
+            .http(HttpOptions::new().client(client).header("x-trace", "1"));
+        let completion: Completion = completion(&Prompt::create("你好"), options.borrow())
+            .await
+            .unwrap()
+            .into();
+        assert_eq!(completion.content.as_deref(), Some("你好"));
+    }
+
+    #[tokio::test]
+    async fn test_logprobs() {
+        let chunks = [
+            json!({"choices": [{"index": 0, "delta": {"content": "Yes"}, "logprobs": {"content": [
+                {"token": "Yes", "logprob": -0.1, "bytes": [89, 101, 115], "top_logprobs": [
+                    {"token": "Yes", "logprob": -0.1, "bytes": [89, 101, 115]},
+                    {"token": "No", "logprob": -2.4, "bytes": [78, 111]},
+                ]},
+            ]}}]}),
+            json!({"choices": [{"index": 0, "delta": {"content": "."}, "logprobs": {"content": [
+                {"token": ".", "logprob": 0.0, "bytes": null, "top_logprobs": []},
+            ]}}]}),
+        ];
+        let body = chunks
+            .iter()
+            .map(|chunk| format!("data: {chunk}\n\n"))
+            .collect::<String>();
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_partial_json(
+                json!({ "logprobs": true, "top_logprobs": 2 }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .mount(&server)
+            .await;
+        let options = OpenAIModelOptions::new()
+            .base_url(server.uri())
+            .top_logprobs(2);
+        let completion = stream(&Prompt::create("Is it?"), options.borrow())
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let logprobs = completion.logprobs.unwrap();
+        assert_eq!(
+            logprobs
+                .iter()
+                .map(|logprob| logprob.token.as_str())
+                .collect::<Vec<_>>(),
+            ["Yes", "."]
+        );
+        assert_eq!(logprobs[0].bytes.as_deref(), Some("Yes".as_bytes()));
+        assert_eq!(logprobs[0].top_logprobs[1].token, "No");
+        assert_eq!(logprobs[0].top_logprobs[1].logprob, -2.4);
+        assert_eq!(logprobs[1].bytes, None);
+    }
+
+    #[tokio::test]
+    async fn test_sampling_parameters() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": "你好"}}],
+            })))
+            .mount(&server)
+            .await;
+        let defaults = OpenAIModelOptions::new()
+            .base_url(server.uri())
+            .model("gpt-4o")
+            .temperature(0.5)
+            .seed(7)
+            .stop(["\n\n"]);
+        let overrides = OpenAIModelOptions::new()
+            .temperature(0.0)
+            .logit_bias(50256, -100.0)
+            .user("user-1");
+        completion(&Prompt::create("你好"), defaults.merge(&overrides))
+            .await
+            .unwrap();
+        let requests = server.received_requests().await.unwrap();
+        let body = requests[0].body_json::<Value>().unwrap();
+        let parameters = [
+            "temperature",
+            "top_p",
+            "max_tokens",
+            "seed",
+            "stop",
+            "logit_bias",
+            "user",
+        ]
+        .map(|key| body.get(key).cloned());
+        assert_eq!(
+            parameters,
+            [
+                Some(json!(0.0)),
+                None,
+                None,
+                Some(json!(7)),
+                Some(json!(["\n\n"])),
+                Some(json!({ "50256": -100.0 })),
+                Some(json!("user-1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_extra_body() {
+        let defaults = OpenAIModelOptions::new()
+            .extra("enable_thinking", true)
+            .extra(
+                "chat_template_kwargs",
+                json!({ "enable_thinking": true, "lang": "zh" }),
+            );
+        let overrides = OpenAIModelOptions::new()
+            .extra("top_k", 20)
+            .extra("chat_template_kwargs", json!({ "enable_thinking": false }));
+        let mut body = json!({ "model": "qwen3", "top_k": 1 });
+        for extra_body in defaults.merge(&overrides).extra_body.into_iter().flatten() {
+            merge(&mut body, extra_body);
+        }
+        assert_eq!(
+            body,
+            json!({
+                "model": "qwen3",
+                "top_k": 20,
+                "enable_thinking": true,
+                "chat_template_kwargs": { "enable_thinking": false, "lang": "zh" },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reasoning() {
+        let cases = [
+            (
+                Reasoning::new().effort(ReasoningEffort::High),
+                false,
+                json!({ "reasoning_effort": "high" }),
+            ),
+            (
+                Reasoning::new().budget_tokens(2048).include_thoughts(true),
+                false,
+                json!({ "reasoning_effort": "medium" }),
+            ),
+            (
+                Reasoning::new().budget_tokens(2048),
+                true,
+                json!({ "reasoning_effort": "medium", "enable_thinking": true, "thinking_budget": 2048 }),
+            ),
+            (
+                Reasoning::new().include_thoughts(false),
+                true,
+                json!({ "enable_thinking": false }),
+            ),
+        ];
+        for (reasoning, thinking_params, expected) in cases {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(body_partial_json(&expected))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "choices": [{"index": 0, "message": {"content": "Hi"}}],
+                })))
+                .mount(&server)
+                .await;
+            let options = OpenAIModelOptions::new()
+                .base_url(server.uri())
+                .reasoning(reasoning)
+                .thinking_params(thinking_params);
+            completion(&Prompt::create("Hi"), options.borrow())
+                .await
+                .unwrap();
+            let body = server.received_requests().await.unwrap()[0]
+                .body_json::<Value>()
+                .unwrap();
+            assert_eq!(body.get("enable_thinking").is_some(), thinking_params);
+        }
+    }
 }
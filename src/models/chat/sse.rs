@@ -0,0 +1,268 @@
+use async_stream::stream;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{error::ApiError, Error, Result, Stream};
+
+/// A server-sent event as defined by the WHATWG `EventSource` specification.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub(crate) struct Event {
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+    pub retry: Option<u64>,
+}
+
+impl Event {
+    pub(crate) fn is_done(&self) -> bool {
+        self.data.trim() == "[DONE]"
+    }
+
+    /// Parses the event data, turning malformed JSON and `{"error": ...}` payloads into errors.
+    pub(crate) fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        let value: Value = serde_json::from_str(&self.data)
+            .map_err(|err| Error::decode(format!("'{}': {err}", self.data)))?;
+        if let Some(error) = value.get("error") {
+            return Err(Error::api(ApiError::from_value(error)));
+        }
+        serde_json::from_value(value)
+            .map_err(|err| Error::decode(format!("'{}': {err}", self.data)))
+    }
+}
+
+/// Incremental SSE decoder that can be fed arbitrary byte chunks.
+///
+/// Bytes are buffered until a full line is available, so neither events nor multi-byte UTF-8
+/// characters split across network boundaries are corrupted.
+#[derive(Default)]
+pub(crate) struct Decoder {
+    buffer: Vec<u8>,
+    started: bool,
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl Decoder {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Vec<Event> {
+        self.buffer.extend_from_slice(chunk);
+        if !self.started {
+            if self.buffer.len() < 3 && b"\xEF\xBB\xBF".starts_with(&self.buffer) {
+                return Vec::new();
+            }
+            if self.buffer.starts_with(b"\xEF\xBB\xBF") {
+                self.buffer.drain(..3);
+            }
+            self.started = true;
+        }
+        let mut events = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = self.buffer[start..]
+            .iter()
+            .position(|b| *b == b'\n' || *b == b'\r')
+        {
+            let end = start + offset;
+            let next = match self.buffer[end] {
+                b'\r' if end + 1 == self.buffer.len() => break,
+                b'\r' if self.buffer[end + 1] == b'\n' => end + 2,
+                _ => end + 1,
+            };
+            let line = String::from_utf8_lossy(&self.buffer[start..end]).into_owned();
+            if let Some(event) = self.line(&line) {
+                events.push(event);
+            }
+            start = next;
+        }
+        self.buffer.drain(..start);
+        events
+    }
+
+    /// Flushes the event still pending when the stream ends without a trailing blank line.
+    pub(crate) fn finish(&mut self) -> Option<Event> {
+        let rest = std::mem::take(&mut self.buffer);
+        let rest = rest.strip_suffix(b"\r").unwrap_or(&rest);
+        if !rest.is_empty() {
+            self.line(&String::from_utf8_lossy(rest));
+        }
+        self.line("")
+    }
+
+    fn line(&mut self, line: &str) -> Option<Event> {
+        if line.is_empty() {
+            // Events without data, such as bare `event: ping` keep-alives, are not dispatched.
+            let event = self.event.take();
+            return self
+                .data
+                .take()
+                .filter(|data| !data.is_empty())
+                .map(|data| Event {
+                    event,
+                    data,
+                    id: self.id.clone(),
+                    retry: self.retry,
+                });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_owned()),
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_owned()),
+            },
+            "id" if !value.contains('\0') => self.id = Some(value.to_owned()),
+            "retry" => {
+                if let Ok(retry) = value.parse() {
+                    self.retry = Some(retry);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+pub(crate) fn events(mut response: reqwest::Response) -> Stream<Result<Event>> {
+    let stream = stream! {
+        let mut decoder = Decoder::new();
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    for event in decoder.push(&chunk) {
+                        yield Ok(event);
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    yield Err(err.into());
+                    return;
+                }
+            }
+        }
+        if let Some(event) = decoder.finish() {
+            yield Ok(event);
+        }
+    };
+    Stream::new(Box::pin(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "\u{FEFF}: keep-alive\r\n\
+        event: message_start\r\n\
+        id: 1\r\n\
+        data: {\"text\":\"你好，世界\"}\r\n\
+        \r\n\
+        retry: 3000\n\
+        data: first line\n\
+        data:second line\n\
+        \n\
+        event: ping\r\
+        data\r\
+        \r\
+        data: [DONE]\n\n";
+
+    fn expected() -> Vec<Event> {
+        vec![
+            Event {
+                event: Some("message_start".to_owned()),
+                data: r#"{"text":"你好，世界"}"#.to_owned(),
+                id: Some("1".to_owned()),
+                retry: None,
+            },
+            Event {
+                event: None,
+                data: "first line\nsecond line".to_owned(),
+                id: Some("1".to_owned()),
+                retry: Some(3000),
+            },
+            Event {
+                event: None,
+                data: "[DONE]".to_owned(),
+                id: Some("1".to_owned()),
+                retry: Some(3000),
+            },
+        ]
+    }
+
+    fn decode(chunks: &[&[u8]]) -> Vec<Event> {
+        let mut decoder = Decoder::new();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            events.extend(decoder.push(chunk));
+        }
+        events.extend(decoder.finish());
+        events
+    }
+
+    #[test]
+    fn test_decode_whole() {
+        let events = decode(&[INPUT.as_bytes()]);
+        assert_eq!(events, expected());
+        assert!(events[2].is_done());
+    }
+
+    #[test]
+    fn test_decode_split_at_every_byte() {
+        let bytes = INPUT.as_bytes();
+        for i in 0..=bytes.len() {
+            assert_eq!(
+                decode(&[&bytes[..i], &bytes[i..]]),
+                expected(),
+                "split at {i}"
+            );
+        }
+        let chunks = bytes.chunks(1).collect::<Vec<_>>();
+        assert_eq!(decode(&chunks), expected());
+    }
+
+    #[test]
+    fn test_decode_irregular_chunks() {
+        let bytes = INPUT.as_bytes();
+        for size in 2..13 {
+            let chunks = bytes.chunks(size).collect::<Vec<_>>();
+            assert_eq!(decode(&chunks), expected(), "chunk size {size}");
+        }
+    }
+
+    #[test]
+    fn test_decode_without_trailing_blank_line() {
+        let events = decode(&[b"data: a\n\ndata: b"]);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].data, "b");
+        assert!(decode(&[b"event: ping\n"]).is_empty());
+        assert!(decode(&[b"event: ping\ndata:\n\n"]).is_empty());
+    }
+
+    #[test]
+    fn test_json_errors() {
+        let event = |data: &str| Event {
+            data: data.to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(event(r#"{"a":1}"#).json::<Value>().unwrap()["a"], 1);
+        let err = event(r#"{"error":{"message":"overloaded","type":"overloaded_error"}}"#)
+            .json::<Value>()
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Api(ApiError { kind: Some(kind), .. }) if kind == "overloaded_error")
+        );
+        let err = event(r#"{"a":"#).json::<Value>().unwrap_err();
+        assert!(matches!(err, Error::Decode(_)));
+    }
+}
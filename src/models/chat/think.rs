@@ -0,0 +1,113 @@
+const OPEN: &str = "<think>";
+const CLOSE: &str = "</think>";
+
+/// Text split into the part inside `<think>` tags and the part outside them.
+#[derive(Default, PartialEq, Debug)]
+pub(crate) struct Split {
+    pub reasoning_content: String,
+    pub content: String,
+}
+
+/// Incremental splitter for content that carries its reasoning inline in `<think>` tags.
+///
+/// Text that could be the start of a tag is held back until the next push decides it, so tags
+/// split across deltas are still recognised. The whitespace models put after `</think>` is
+/// dropped.
+#[derive(Default)]
+pub(crate) struct ThinkTags {
+    buffer: String,
+    thinking: bool,
+    trim: bool,
+}
+
+impl ThinkTags {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&mut self, text: &str) -> Split {
+        self.buffer.push_str(text);
+        let mut split = Split::default();
+        loop {
+            let tag = if self.thinking { CLOSE } else { OPEN };
+            if let Some(start) = self.buffer.find(tag) {
+                let text = self.buffer[..start].to_owned();
+                self.emit(&mut split, &text);
+                self.buffer.drain(..start + tag.len());
+                self.thinking = !self.thinking;
+                self.trim = !self.thinking;
+                continue;
+            }
+            // Tags are ASCII and start with '<', so the held back suffix is on a char boundary.
+            let partial = (1..tag.len())
+                .rev()
+                .find(|len| self.buffer.ends_with(&tag[..*len]))
+                .unwrap_or(0);
+            let text = self
+                .buffer
+                .drain(..self.buffer.len() - partial)
+                .collect::<String>();
+            self.emit(&mut split, &text);
+            return split;
+        }
+    }
+
+    /// Flushes held back text, e.g. a lone `<` at the end of the content.
+    pub(crate) fn finish(&mut self) -> Split {
+        let mut split = Split::default();
+        let text = std::mem::take(&mut self.buffer);
+        self.emit(&mut split, &text);
+        split
+    }
+
+    fn emit(&mut self, split: &mut Split, text: &str) {
+        if self.thinking {
+            split.reasoning_content.push_str(text);
+            return;
+        }
+        let text = if self.trim { text.trim_start() } else { text };
+        if !text.is_empty() {
+            self.trim = false;
+        }
+        split.content.push_str(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_across_deltas() {
+        let mut tags = ThinkTags::new();
+        let mut reasoning_content = String::new();
+        let mut content = String::new();
+        let deltas = [
+            "<th", "ink>Hm, ", "1 < 2.</", "thi", "nk>\n", "\nYes", ", 1 <", " 2.",
+        ];
+        for delta in deltas {
+            let split = tags.push(delta);
+            reasoning_content += &split.reasoning_content;
+            content += &split.content;
+        }
+        let split = tags.finish();
+        reasoning_content += &split.reasoning_content;
+        content += &split.content;
+        assert_eq!(reasoning_content, "Hm, 1 < 2.");
+        assert_eq!(content, "Yes, 1 < 2.");
+    }
+
+    #[test]
+    fn test_held_back() {
+        let mut tags = ThinkTags::new();
+        assert_eq!(
+            tags.push("Héllo <thi"),
+            Split {
+                reasoning_content: String::new(),
+                content: "Héllo ".to_owned(),
+            }
+        );
+        assert_eq!(tags.finish().content, "<thi");
+        assert_eq!(ThinkTags::new().push("<think>a</think>b").content, "b");
+    }
+}
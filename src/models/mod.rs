@@ -3,14 +3,36 @@ use std::{
     pin::Pin,
 };
 
+use async_stream::stream;
 use futures::StreamExt;
 
-use crate::{options::ModelOptions, Completion};
+use crate::{
+    completion::CompletionAccumulator, options::ModelOptions, tokenizer, Completion, Result,
+    Tokenizer,
+};
 
 pub mod chat;
 
 pub trait Model: Send + Sync + 'static {
     fn options(&self) -> &ModelOptions;
+
+    /// With a tokenizer, prompts estimated not to fit the context window fail with
+    /// [`Error::ContextWindowExceeded`](crate::Error::ContextWindowExceeded) before they are sent.
+    fn tokenizer(&self) -> Option<&dyn Tokenizer> {
+        None
+    }
+
+    fn context_window(&self, model: &str) -> Option<usize> {
+        tokenizer::context_window(model)
+    }
+}
+
+/// Whether `model` is `key` followed by a snapshot suffix such as `-2024-08-06` or `-0613`.
+pub(crate) fn is_snapshot_of(model: &str, key: &str) -> bool {
+    model
+        .strip_prefix(key)
+        .and_then(|suffix| suffix.strip_prefix('-'))
+        .is_some_and(|suffix| suffix.starts_with(|c: char| c.is_ascii_digit()))
 }
 
 pub struct Stream<T>(InnerStream<T>);
@@ -52,30 +74,54 @@ impl<T> DerefMut for Stream<T> {
     }
 }
 
-impl Stream<Completion> {
-    pub async fn collect(mut self) -> Completion {
-        let mut content = None;
-        let mut reasoning_content = None;
-        let mut usage = None;
+impl Stream<Result<Completion>> {
+    pub async fn collect(mut self) -> Result<Completion> {
+        let mut accumulator = CompletionAccumulator::new();
         while let Some(item) = self.next().await {
-            if let Some(content_chunk) = item.content {
-                *content.get_or_insert_default() += content_chunk.as_str();
-            }
-            if let Some(reasoning_content_chunk) = item.reasoning_content {
-                *reasoning_content.get_or_insert_default() += reasoning_content_chunk.as_str();
-            }
-            if let Some(usage_chunk) = item.usage {
-                usage = Some(usage_chunk);
-            }
+            accumulator.push(item?);
         }
-        Completion::new(content, reasoning_content, usage)
+        accumulator.finish()
+    }
+}
+
+impl From<Stream<Result<Completion>>> for Stream<Result<String>> {
+    fn from(mut stream: Stream<Result<Completion>>) -> Self {
+        let text_stream = stream! {
+            let mut reasoning = false;
+            while let Some(item) = stream.next().await {
+                let item = match item {
+                    Ok(item) => item,
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                };
+                if let Some(reasoning_content) = item.reasoning_content.filter(|s| !s.is_empty()) {
+                    if !reasoning {
+                        yield Ok("<think>".to_string());
+                        reasoning = true;
+                    }
+                    yield Ok(reasoning_content);
+                }
+                if let Some(content) = item.content.filter(|s| !s.is_empty()) {
+                    if reasoning {
+                        yield Ok("</think>".to_string());
+                        reasoning = false;
+                    }
+                    yield Ok(content);
+                }
+            }
+        };
+        text_stream.into()
     }
 }
 
-impl Stream<String> {
-    pub async fn collect(self) -> String {
-        self.into_inner()
-            .fold(String::new(), async |acc, item| acc + &item.to_string())
-            .await
+impl Stream<Result<String>> {
+    pub async fn collect(mut self) -> Result<String> {
+        let mut text = String::new();
+        while let Some(item) = self.next().await {
+            text += &item?;
+        }
+        Ok(text)
     }
 }
@@ -1,45 +1,249 @@
-use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(untagged)]
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::{
+    http::HttpOptions,
+    provider::{CustomProvider, Provider},
+    reasoning::Reasoning,
+    response_format::ResponseFormat,
+    retry::RetryPolicy,
+    tool::ToolDefinition,
+    Error, Result,
+};
+
+/// Serialized with a `provider` field naming the backend, e.g. `{"provider": "anthropic", ...}`,
+/// since the options of every backend are all optional fields that would otherwise match any of
+/// them. Options without a `provider` are read as OpenAI options, and unset options as `null`.
+#[derive(Clone, Debug, Default)]
+#[allow(clippy::large_enum_variant)]
 pub enum ModelOptions {
     OpenAI(OpenAIModelOptions),
+    Anthropic(AnthropicModelOptions),
+    Ollama(OllamaModelOptions),
+    Gemini(GeminiModelOptions),
+    Custom(CustomProvider),
     #[doc(hidden)]
+    #[default]
     Whatever,
 }
 
+#[derive(Serialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+enum TaggedRef<'a> {
+    OpenAI(&'a OpenAIModelOptions),
+    Anthropic(&'a AnthropicModelOptions),
+    Ollama(&'a OllamaModelOptions),
+    Gemini(&'a GeminiModelOptions),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+#[allow(clippy::large_enum_variant)]
+enum Tagged {
+    OpenAI(OpenAIModelOptions),
+    Anthropic(AnthropicModelOptions),
+    Ollama(OllamaModelOptions),
+    Gemini(GeminiModelOptions),
+}
+
+impl Serialize for ModelOptions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let tagged = match self {
+            Self::OpenAI(options) => TaggedRef::OpenAI(options),
+            Self::Anthropic(options) => TaggedRef::Anthropic(options),
+            Self::Ollama(options) => TaggedRef::Ollama(options),
+            Self::Gemini(options) => TaggedRef::Gemini(options),
+            Self::Custom(_) => {
+                return Err(ser::Error::custom("a custom provider cannot be serialized"))
+            }
+            Self::Whatever => return serializer.serialize_unit(),
+        };
+        tagged.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ModelOptions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let Some(options) = Option::<Map<String, Value>>::deserialize(deserializer)? else {
+            return Ok(Self::Whatever);
+        };
+        if !options.contains_key("provider") {
+            return OpenAIModelOptions::deserialize(Value::Object(options))
+                .map(Self::OpenAI)
+                .map_err(de::Error::custom);
+        }
+        let options =
+            match Tagged::deserialize(Value::Object(options)).map_err(de::Error::custom)? {
+                Tagged::OpenAI(options) => Self::OpenAI(options),
+                Tagged::Anthropic(options) => Self::Anthropic(options),
+                Tagged::Ollama(options) => Self::Ollama(options),
+                Tagged::Gemini(options) => Self::Gemini(options),
+            };
+        Ok(options)
+    }
+}
+
 impl ModelOptions {
     pub fn openai() -> OpenAIModelOptions {
         OpenAIModelOptions::new()
     }
-}
 
-impl Default for ModelOptions {
-    fn default() -> Self {
-        Self::Whatever
+    pub fn anthropic() -> AnthropicModelOptions {
+        AnthropicModelOptions::new()
+    }
+
+    pub fn ollama() -> OllamaModelOptions {
+        OllamaModelOptions::new()
+    }
+
+    pub fn gemini() -> GeminiModelOptions {
+        GeminiModelOptions::new()
+    }
+
+    pub fn custom<P: Provider + 'static>(provider: P) -> Self {
+        Self::Custom(CustomProvider(Arc::new(provider)))
     }
 }
 
 pub(crate) enum BorrowedModelOptions<'a> {
     OpenAI(BorrowedOpenAIModelOptions<'a>),
+    Anthropic(BorrowedAnthropicModelOptions<'a>),
+    Ollama(BorrowedOllamaModelOptions<'a>),
+    Gemini(BorrowedGeminiModelOptions<'a>),
+    Custom(&'a CustomProvider),
     Whatever,
 }
 
+impl<'a> BorrowedModelOptions<'a> {
+    pub(crate) fn model(&self) -> Option<&'a str> {
+        match self {
+            Self::OpenAI(options) => options.model,
+            Self::Anthropic(options) => options.model,
+            Self::Ollama(options) => options.model,
+            Self::Gemini(options) => options.model,
+            Self::Custom(_) | Self::Whatever => None,
+        }
+    }
+
+    pub(crate) fn tools(&self) -> Option<&'a [ToolDefinition]> {
+        match self {
+            Self::OpenAI(options) => options.tools,
+            Self::Anthropic(options) => options.tools,
+            Self::Ollama(options) => options.tools,
+            Self::Gemini(options) => options.tools,
+            Self::Custom(_) | Self::Whatever => None,
+        }
+    }
+
+    pub(crate) fn response_format(&self) -> Option<&'a ResponseFormat> {
+        match self {
+            Self::OpenAI(options) => options.response_format,
+            _ => None,
+        }
+    }
+
+    pub(crate) fn provider(self) -> Result<Box<dyn Provider + 'a>> {
+        match self {
+            Self::OpenAI(options) => Ok(Box::new(options)),
+            Self::Anthropic(options) => Ok(Box::new(options)),
+            Self::Ollama(options) => Ok(Box::new(options)),
+            Self::Gemini(options) => Ok(Box::new(options)),
+            Self::Custom(provider) => Ok(Box::new(provider.0.clone())),
+            Self::Whatever => Err(Error::invalid_request(
+                "no provider is configured in 'ModelOptions'",
+            )),
+        }
+    }
+}
+
 impl ModelOptions {
     pub(crate) fn borrow(&self) -> BorrowedModelOptions<'_> {
         match self {
             Self::OpenAI(options) => options.borrow().into(),
+            Self::Anthropic(options) => options.borrow().into(),
+            Self::Ollama(options) => options.borrow().into(),
+            Self::Gemini(options) => options.borrow().into(),
+            Self::Custom(provider) => BorrowedModelOptions::Custom(provider),
             Self::Whatever => BorrowedModelOptions::Whatever,
         }
     }
 
+    /// Adds `tools` to the tools configured in these options, or in `defaults` when these
+    /// options leave them unset; a configured tool with the same name as one of `tools` is
+    /// replaced.
+    pub(crate) fn with_tools(self, defaults: &Self, tools: Vec<ToolDefinition>) -> Result<Self> {
+        let mut merged = defaults
+            .merge(&self)
+            .tools()
+            .unwrap_or_default()
+            .iter()
+            .filter(|tool| tools.iter().all(|other| other.name() != tool.name()))
+            .cloned()
+            .collect::<Vec<_>>();
+        merged.extend(tools);
+        let tools = merged;
+        Ok(match (self, defaults) {
+            (Self::OpenAI(options), _) => options.tools(tools).into(),
+            (Self::Anthropic(options), _) => options.tools(tools).into(),
+            (Self::Ollama(options), _) => options.tools(tools).into(),
+            (Self::Gemini(options), _) => options.tools(tools).into(),
+            (Self::Whatever, Self::OpenAI(_)) => OpenAIModelOptions::new().tools(tools).into(),
+            (Self::Whatever, Self::Anthropic(_)) => {
+                AnthropicModelOptions::new().tools(tools).into()
+            }
+            (Self::Whatever, Self::Ollama(_)) => OllamaModelOptions::new().tools(tools).into(),
+            (Self::Whatever, Self::Gemini(_)) => GeminiModelOptions::new().tools(tools).into(),
+            (Self::Custom(_), _) | (Self::Whatever, Self::Custom(_)) => {
+                return Err(Error::invalid_request(
+                    "a custom provider cannot be given tool definitions",
+                ))
+            }
+            (Self::Whatever, Self::Whatever) => {
+                return Err(Error::invalid_request(
+                    "no provider is configured in 'ModelOptions' to carry tool definitions",
+                ))
+            }
+        })
+    }
+
+    /// Whether the provider takes a `response_format`; for the others the format is put into
+    /// the prompt instead.
+    pub(crate) fn supports_response_format(&self, defaults: &Self) -> bool {
+        matches!(
+            (self, defaults),
+            (Self::OpenAI(_), _) | (Self::Whatever, Self::OpenAI(_))
+        )
+    }
+
+    /// Asks for `response_format` where the provider supports it.
+    pub(crate) fn with_response_format(self, defaults: &Self, format: ResponseFormat) -> Self {
+        match (self, defaults) {
+            (Self::OpenAI(options), _) => options.response_format(format).into(),
+            (Self::Whatever, Self::OpenAI(_)) => {
+                OpenAIModelOptions::new().response_format(format).into()
+            }
+            (options, _) => options,
+        }
+    }
+
     pub(crate) fn merge<'a>(&'a self, other: &'a Self) -> BorrowedModelOptions<'a> {
         match (self, other) {
             (Self::OpenAI(options), Self::OpenAI(other_options)) => {
                 options.merge(other_options).into()
             }
+            (Self::Anthropic(options), Self::Anthropic(other_options)) => {
+                options.merge(other_options).into()
+            }
+            (Self::Ollama(options), Self::Ollama(other_options)) => {
+                options.merge(other_options).into()
+            }
+            (Self::Gemini(options), Self::Gemini(other_options)) => {
+                options.merge(other_options).into()
+            }
             (_, Self::Whatever) => self.borrow(),
-            (Self::Whatever, _) => other.borrow(),
+            _ => other.borrow(),
         }
     }
 }
@@ -55,6 +259,72 @@ pub struct OpenAIModelOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub tools: Option<Vec<ToolDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub logit_bias: Option<BTreeMap<u32, f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub logprobs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub top_logprobs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub extra_body: Option<Map<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reasoning: Option<Reasoning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub think_tags: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub send_reasoning: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub thinking_params: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub http: Option<HttpOptions>,
 }
 
 impl OpenAIModelOptions {
@@ -63,6 +333,28 @@ impl OpenAIModelOptions {
             base_url: None,
             model: None,
             api_key: None,
+            tools: None,
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
+            max_completion_tokens: None,
+            stop: None,
+            seed: None,
+            presence_penalty: None,
+            frequency_penalty: None,
+            logit_bias: None,
+            n: None,
+            user: None,
+            logprobs: None,
+            top_logprobs: None,
+            extra_body: None,
+            response_format: None,
+            reasoning: None,
+            think_tags: None,
+            send_reasoning: None,
+            thinking_params: None,
+            retry: None,
+            http: None,
         }
     }
 
@@ -80,6 +372,146 @@ impl OpenAIModelOptions {
         self.api_key = Some(api_key.as_ref().to_owned());
         self
     }
+
+    pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn tool(mut self, tool: ToolDefinition) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn max_completion_tokens(mut self, max_completion_tokens: u32) -> Self {
+        self.max_completion_tokens = Some(max_completion_tokens);
+        self
+    }
+
+    pub fn stop<T: AsRef<str>>(mut self, stop: impl IntoIterator<Item = T>) -> Self {
+        self.stop = Some(stop.into_iter().map(|s| s.as_ref().to_owned()).collect());
+        self
+    }
+
+    pub fn seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn presence_penalty(mut self, presence_penalty: f32) -> Self {
+        self.presence_penalty = Some(presence_penalty);
+        self
+    }
+
+    pub fn frequency_penalty(mut self, frequency_penalty: f32) -> Self {
+        self.frequency_penalty = Some(frequency_penalty);
+        self
+    }
+
+    pub fn logit_bias(mut self, token: u32, bias: f32) -> Self {
+        self.logit_bias
+            .get_or_insert_with(BTreeMap::new)
+            .insert(token, bias);
+        self
+    }
+
+    pub fn n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    pub fn user<T: AsRef<str>>(mut self, user: T) -> Self {
+        self.user = Some(user.as_ref().to_owned());
+        self
+    }
+
+    pub fn logprobs(mut self, logprobs: bool) -> Self {
+        self.logprobs = Some(logprobs);
+        self
+    }
+
+    /// Also returns the `top_logprobs` most likely alternatives for every token; implies
+    /// `logprobs`.
+    pub fn top_logprobs(mut self, top_logprobs: u32) -> Self {
+        self.logprobs = Some(true);
+        self.top_logprobs = Some(top_logprobs);
+        self
+    }
+
+    /// Vendor-specific fields deep-merged into the request body, e.g. `enable_thinking`.
+    pub fn extra_body(mut self, extra_body: Map<String, Value>) -> Self {
+        self.extra_body = Some(extra_body);
+        self
+    }
+
+    pub fn response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    pub fn extra<K: AsRef<str>, V: Into<Value>>(mut self, key: K, value: V) -> Self {
+        self.extra_body
+            .get_or_insert_with(Map::new)
+            .insert(key.as_ref().to_owned(), value.into());
+        self
+    }
+
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    /// Moves `<think>...</think>` found in the content into `reasoning_content`, for models that
+    /// reason inline instead of in a separate field.
+    pub fn think_tags(mut self, think_tags: bool) -> Self {
+        self.think_tags = Some(think_tags);
+        self
+    }
+
+    /// Sends the reasoning of earlier assistant messages back as `reasoning_content`, as DeepSeek
+    /// expects during tool calls. Off by default since other servers reject the field.
+    pub fn send_reasoning(mut self, send_reasoning: bool) -> Self {
+        self.send_reasoning = Some(send_reasoning);
+        self
+    }
+
+    /// Also sends `reasoning` as `enable_thinking` and `thinking_budget`, as Qwen-style servers
+    /// expect. Off by default since OpenAI rejects the fields.
+    pub fn thinking_params(mut self, thinking_params: bool) -> Self {
+        self.thinking_params = Some(thinking_params);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn http(mut self, http: HttpOptions) -> Self {
+        self.http = Some(http);
+        self
+    }
+}
+
+impl Default for OpenAIModelOptions {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl From<OpenAIModelOptions> for ModelOptions {
@@ -88,10 +520,34 @@ impl From<OpenAIModelOptions> for ModelOptions {
     }
 }
 
+#[derive(Clone, Copy)]
 pub(crate) struct BorrowedOpenAIModelOptions<'a> {
     pub model: Option<&'a str>,
     pub base_url: Option<&'a str>,
     pub api_key: Option<&'a str>,
+    pub tools: Option<&'a [ToolDefinition]>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub max_completion_tokens: Option<u32>,
+    pub stop: Option<&'a [String]>,
+    pub seed: Option<i64>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub logit_bias: Option<&'a BTreeMap<u32, f32>>,
+    pub n: Option<u32>,
+    pub user: Option<&'a str>,
+    pub logprobs: Option<bool>,
+    pub top_logprobs: Option<u32>,
+    /// The model defaults followed by the per-call overrides, deep-merged in that order.
+    pub extra_body: [Option<&'a Map<String, Value>>; 2],
+    pub response_format: Option<&'a ResponseFormat>,
+    pub reasoning: Option<Reasoning>,
+    pub think_tags: Option<bool>,
+    pub send_reasoning: Option<bool>,
+    pub thinking_params: Option<bool>,
+    pub retry: Option<&'a RetryPolicy>,
+    pub http: Option<&'a HttpOptions>,
 }
 
 impl OpenAIModelOptions {
@@ -100,6 +556,28 @@ impl OpenAIModelOptions {
             model: self.model.as_deref(),
             base_url: self.base_url.as_deref(),
             api_key: self.api_key.as_deref(),
+            tools: self.tools.as_deref(),
+            temperature: self.temperature,
+            top_p: self.top_p,
+            max_tokens: self.max_tokens,
+            max_completion_tokens: self.max_completion_tokens,
+            stop: self.stop.as_deref(),
+            seed: self.seed,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+            logit_bias: self.logit_bias.as_ref(),
+            n: self.n,
+            user: self.user.as_deref(),
+            logprobs: self.logprobs,
+            top_logprobs: self.top_logprobs,
+            extra_body: [self.extra_body.as_ref(), None],
+            response_format: self.response_format.as_ref(),
+            reasoning: self.reasoning,
+            think_tags: self.think_tags,
+            send_reasoning: self.send_reasoning,
+            thinking_params: self.thinking_params,
+            retry: self.retry.as_ref(),
+            http: self.http.as_ref(),
         }
     }
 
@@ -108,6 +586,31 @@ impl OpenAIModelOptions {
             model: other.model.as_deref().or(self.model.as_deref()),
             base_url: other.base_url.as_deref().or(self.base_url.as_deref()),
             api_key: other.api_key.as_deref().or(self.api_key.as_deref()),
+            tools: other.tools.as_deref().or(self.tools.as_deref()),
+            temperature: other.temperature.or(self.temperature),
+            top_p: other.top_p.or(self.top_p),
+            max_tokens: other.max_tokens.or(self.max_tokens),
+            max_completion_tokens: other.max_completion_tokens.or(self.max_completion_tokens),
+            stop: other.stop.as_deref().or(self.stop.as_deref()),
+            seed: other.seed.or(self.seed),
+            presence_penalty: other.presence_penalty.or(self.presence_penalty),
+            frequency_penalty: other.frequency_penalty.or(self.frequency_penalty),
+            logit_bias: other.logit_bias.as_ref().or(self.logit_bias.as_ref()),
+            n: other.n.or(self.n),
+            user: other.user.as_deref().or(self.user.as_deref()),
+            logprobs: other.logprobs.or(self.logprobs),
+            top_logprobs: other.top_logprobs.or(self.top_logprobs),
+            extra_body: [self.extra_body.as_ref(), other.extra_body.as_ref()],
+            response_format: other
+                .response_format
+                .as_ref()
+                .or(self.response_format.as_ref()),
+            reasoning: other.reasoning.or(self.reasoning),
+            think_tags: other.think_tags.or(self.think_tags),
+            send_reasoning: other.send_reasoning.or(self.send_reasoning),
+            thinking_params: other.thinking_params.or(self.thinking_params),
+            retry: other.retry.as_ref().or(self.retry.as_ref()),
+            http: other.http.as_ref().or(self.http.as_ref()),
         }
     }
 }
@@ -117,3 +620,467 @@ impl<'a> From<BorrowedOpenAIModelOptions<'a>> for BorrowedModelOptions<'a> {
         Self::OpenAI(options)
     }
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AnthropicModelOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub tools: Option<Vec<ToolDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reasoning: Option<Reasoning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub http: Option<HttpOptions>,
+}
+
+impl AnthropicModelOptions {
+    pub fn new() -> Self {
+        Self {
+            base_url: None,
+            model: None,
+            api_key: None,
+            max_tokens: None,
+            tools: None,
+            reasoning: None,
+            retry: None,
+            http: None,
+        }
+    }
+
+    pub fn model<T: AsRef<str>>(mut self, model: T) -> Self {
+        self.model = Some(model.as_ref().to_owned());
+        self
+    }
+
+    pub fn base_url<T: AsRef<str>>(mut self, base_url: T) -> Self {
+        self.base_url = Some(base_url.as_ref().to_owned());
+        self
+    }
+
+    pub fn api_key<T: AsRef<str>>(mut self, api_key: T) -> Self {
+        self.api_key = Some(api_key.as_ref().to_owned());
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn tool(mut self, tool: ToolDefinition) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn http(mut self, http: HttpOptions) -> Self {
+        self.http = Some(http);
+        self
+    }
+}
+
+impl Default for AnthropicModelOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<AnthropicModelOptions> for ModelOptions {
+    fn from(options: AnthropicModelOptions) -> Self {
+        Self::Anthropic(options)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct BorrowedAnthropicModelOptions<'a> {
+    pub model: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub api_key: Option<&'a str>,
+    pub max_tokens: Option<u32>,
+    pub tools: Option<&'a [ToolDefinition]>,
+    pub reasoning: Option<Reasoning>,
+    pub retry: Option<&'a RetryPolicy>,
+    pub http: Option<&'a HttpOptions>,
+}
+
+impl AnthropicModelOptions {
+    pub(crate) fn borrow(&self) -> BorrowedAnthropicModelOptions<'_> {
+        BorrowedAnthropicModelOptions {
+            model: self.model.as_deref(),
+            base_url: self.base_url.as_deref(),
+            api_key: self.api_key.as_deref(),
+            max_tokens: self.max_tokens,
+            tools: self.tools.as_deref(),
+            reasoning: self.reasoning,
+            retry: self.retry.as_ref(),
+            http: self.http.as_ref(),
+        }
+    }
+
+    pub(crate) fn merge<'a>(&'a self, other: &'a Self) -> BorrowedAnthropicModelOptions<'a> {
+        BorrowedAnthropicModelOptions {
+            model: other.model.as_deref().or(self.model.as_deref()),
+            base_url: other.base_url.as_deref().or(self.base_url.as_deref()),
+            api_key: other.api_key.as_deref().or(self.api_key.as_deref()),
+            max_tokens: other.max_tokens.or(self.max_tokens),
+            tools: other.tools.as_deref().or(self.tools.as_deref()),
+            reasoning: other.reasoning.or(self.reasoning),
+            retry: other.retry.as_ref().or(self.retry.as_ref()),
+            http: other.http.as_ref().or(self.http.as_ref()),
+        }
+    }
+}
+
+impl<'a> From<BorrowedAnthropicModelOptions<'a>> for BorrowedModelOptions<'a> {
+    fn from(options: BorrowedAnthropicModelOptions<'a>) -> Self {
+        Self::Anthropic(options)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OllamaModelOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub tools: Option<Vec<ToolDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reasoning: Option<Reasoning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub http: Option<HttpOptions>,
+}
+
+impl OllamaModelOptions {
+    pub fn new() -> Self {
+        Self {
+            base_url: None,
+            model: None,
+            api_key: None,
+            tools: None,
+            reasoning: None,
+            retry: None,
+            http: None,
+        }
+    }
+
+    pub fn model<T: AsRef<str>>(mut self, model: T) -> Self {
+        self.model = Some(model.as_ref().to_owned());
+        self
+    }
+
+    pub fn base_url<T: AsRef<str>>(mut self, base_url: T) -> Self {
+        self.base_url = Some(base_url.as_ref().to_owned());
+        self
+    }
+
+    pub fn api_key<T: AsRef<str>>(mut self, api_key: T) -> Self {
+        self.api_key = Some(api_key.as_ref().to_owned());
+        self
+    }
+
+    pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn tool(mut self, tool: ToolDefinition) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn http(mut self, http: HttpOptions) -> Self {
+        self.http = Some(http);
+        self
+    }
+}
+
+impl Default for OllamaModelOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<OllamaModelOptions> for ModelOptions {
+    fn from(options: OllamaModelOptions) -> Self {
+        Self::Ollama(options)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct BorrowedOllamaModelOptions<'a> {
+    pub model: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub api_key: Option<&'a str>,
+    pub tools: Option<&'a [ToolDefinition]>,
+    pub reasoning: Option<Reasoning>,
+    pub retry: Option<&'a RetryPolicy>,
+    pub http: Option<&'a HttpOptions>,
+}
+
+impl OllamaModelOptions {
+    pub(crate) fn borrow(&self) -> BorrowedOllamaModelOptions<'_> {
+        BorrowedOllamaModelOptions {
+            model: self.model.as_deref(),
+            base_url: self.base_url.as_deref(),
+            api_key: self.api_key.as_deref(),
+            tools: self.tools.as_deref(),
+            reasoning: self.reasoning,
+            retry: self.retry.as_ref(),
+            http: self.http.as_ref(),
+        }
+    }
+
+    pub(crate) fn merge<'a>(&'a self, other: &'a Self) -> BorrowedOllamaModelOptions<'a> {
+        BorrowedOllamaModelOptions {
+            model: other.model.as_deref().or(self.model.as_deref()),
+            base_url: other.base_url.as_deref().or(self.base_url.as_deref()),
+            api_key: other.api_key.as_deref().or(self.api_key.as_deref()),
+            tools: other.tools.as_deref().or(self.tools.as_deref()),
+            reasoning: other.reasoning.or(self.reasoning),
+            retry: other.retry.as_ref().or(self.retry.as_ref()),
+            http: other.http.as_ref().or(self.http.as_ref()),
+        }
+    }
+}
+
+impl<'a> From<BorrowedOllamaModelOptions<'a>> for BorrowedModelOptions<'a> {
+    fn from(options: BorrowedOllamaModelOptions<'a>) -> Self {
+        Self::Ollama(options)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GeminiModelOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub tools: Option<Vec<ToolDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reasoning: Option<Reasoning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub http: Option<HttpOptions>,
+}
+
+impl GeminiModelOptions {
+    pub fn new() -> Self {
+        Self {
+            base_url: None,
+            model: None,
+            api_key: None,
+            tools: None,
+            reasoning: None,
+            retry: None,
+            http: None,
+        }
+    }
+
+    pub fn model<T: AsRef<str>>(mut self, model: T) -> Self {
+        self.model = Some(model.as_ref().to_owned());
+        self
+    }
+
+    pub fn base_url<T: AsRef<str>>(mut self, base_url: T) -> Self {
+        self.base_url = Some(base_url.as_ref().to_owned());
+        self
+    }
+
+    pub fn api_key<T: AsRef<str>>(mut self, api_key: T) -> Self {
+        self.api_key = Some(api_key.as_ref().to_owned());
+        self
+    }
+
+    pub fn tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn tool(mut self, tool: ToolDefinition) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+
+    pub fn reasoning(mut self, reasoning: Reasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn http(mut self, http: HttpOptions) -> Self {
+        self.http = Some(http);
+        self
+    }
+}
+
+impl Default for GeminiModelOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<GeminiModelOptions> for ModelOptions {
+    fn from(options: GeminiModelOptions) -> Self {
+        Self::Gemini(options)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct BorrowedGeminiModelOptions<'a> {
+    pub model: Option<&'a str>,
+    pub base_url: Option<&'a str>,
+    pub api_key: Option<&'a str>,
+    pub tools: Option<&'a [ToolDefinition]>,
+    pub reasoning: Option<Reasoning>,
+    pub retry: Option<&'a RetryPolicy>,
+    pub http: Option<&'a HttpOptions>,
+}
+
+impl GeminiModelOptions {
+    pub(crate) fn borrow(&self) -> BorrowedGeminiModelOptions<'_> {
+        BorrowedGeminiModelOptions {
+            model: self.model.as_deref(),
+            base_url: self.base_url.as_deref(),
+            api_key: self.api_key.as_deref(),
+            tools: self.tools.as_deref(),
+            reasoning: self.reasoning,
+            retry: self.retry.as_ref(),
+            http: self.http.as_ref(),
+        }
+    }
+
+    pub(crate) fn merge<'a>(&'a self, other: &'a Self) -> BorrowedGeminiModelOptions<'a> {
+        BorrowedGeminiModelOptions {
+            model: other.model.as_deref().or(self.model.as_deref()),
+            base_url: other.base_url.as_deref().or(self.base_url.as_deref()),
+            api_key: other.api_key.as_deref().or(self.api_key.as_deref()),
+            tools: other.tools.as_deref().or(self.tools.as_deref()),
+            reasoning: other.reasoning.or(self.reasoning),
+            retry: other.retry.as_ref().or(self.retry.as_ref()),
+            http: other.http.as_ref().or(self.http.as_ref()),
+        }
+    }
+}
+
+impl<'a> From<BorrowedGeminiModelOptions<'a>> for BorrowedModelOptions<'a> {
+    fn from(options: BorrowedGeminiModelOptions<'a>) -> Self {
+        Self::Gemini(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let options: [ModelOptions; 4] = [
+            ModelOptions::openai().model("gpt-4o").into(),
+            ModelOptions::anthropic().model("claude-sonnet-4-5").into(),
+            ModelOptions::ollama().model("qwen3").into(),
+            ModelOptions::gemini().model("gemini-2.5-flash").into(),
+        ];
+        for (options, provider) in options
+            .iter()
+            .zip(["openai", "anthropic", "ollama", "gemini"])
+        {
+            let value = serde_json::to_value(options).unwrap();
+            assert_eq!(value["provider"], provider);
+            let round_trip = serde_json::from_value::<ModelOptions>(value.clone()).unwrap();
+            assert_eq!(
+                std::mem::discriminant(&round_trip),
+                std::mem::discriminant(options)
+            );
+            assert_eq!(serde_json::to_value(&round_trip).unwrap(), value);
+        }
+        assert!(matches!(
+            serde_json::from_value(json!({"model": "gpt-4o", "base_url": "http://localhost"})),
+            Ok(ModelOptions::OpenAI(OpenAIModelOptions { model: Some(model), .. })) if model == "gpt-4o"
+        ));
+        assert_eq!(
+            serde_json::to_value(ModelOptions::default()).unwrap(),
+            Value::Null
+        );
+        assert!(matches!(
+            serde_json::from_value(Value::Null),
+            Ok(ModelOptions::Whatever)
+        ));
+        assert!(serde_json::from_value::<ModelOptions>(json!({"provider": "mistral"})).is_err());
+        assert!(matches!(
+            serde_json::from_value(json!({"provider": "anthropic", "max_tokens": 1024})),
+            Ok(ModelOptions::Anthropic(AnthropicModelOptions {
+                max_tokens: Some(1024),
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    collections::BTreeMap,
+    iter::Sum,
+    ops::{Add, AddAssign, Bound},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{models::is_snapshot_of, Completion, Error, Result, Usage};
+
+const MILLION: f64 = 1_000_000.0;
+
+/// Prices per million tokens. Cached input defaults to the input price and reasoning to the
+/// output price.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub struct Price {
+    pub input: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub cached_input: Option<f64>,
+    pub output: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub reasoning: Option<f64>,
+}
+
+impl Price {
+    pub fn new(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            ..Default::default()
+        }
+    }
+
+    pub fn cached_input(mut self, cached_input: f64) -> Self {
+        self.cached_input = Some(cached_input);
+        self
+    }
+
+    pub fn reasoning(mut self, reasoning: f64) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    /// Cached and reasoning tokens are billed at their own price and taken out of the prompt and
+    /// completion counts they are part of.
+    pub fn cost(&self, usage: &Usage) -> Cost {
+        let cached_tokens = usage.cached_tokens().min(usage.prompt_tokens);
+        let reasoning_tokens = usage.reasoning_tokens().min(usage.completion_tokens);
+        Cost {
+            input: (usage.prompt_tokens - cached_tokens) as f64 * self.input / MILLION,
+            cached_input: cached_tokens as f64 * self.cached_input.unwrap_or(self.input) / MILLION,
+            output: (usage.completion_tokens - reasoning_tokens) as f64 * self.output / MILLION,
+            reasoning: reasoning_tokens as f64 * self.reasoning.unwrap_or(self.output) / MILLION,
+        }
+    }
+}
+
+/// A cost broken down by token kind, in the currency of the prices it was computed from.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub struct Cost {
+    pub input: f64,
+    pub cached_input: f64,
+    pub output: f64,
+    pub reasoning: f64,
+}
+
+impl Cost {
+    pub fn total(&self) -> f64 {
+        self.input + self.cached_input + self.output + self.reasoning
+    }
+}
+
+impl Add for Cost {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            input: self.input + other.input,
+            cached_input: self.cached_input + other.cached_input,
+            output: self.output + other.output,
+            reasoning: self.reasoning + other.reasoning,
+        }
+    }
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for Cost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+/// Prices keyed by provider and then model, e.g. in TOML:
+///
+/// ```toml
+/// [openai."gpt-4o"]
+/// input = 2.5
+/// cached_input = 1.25
+/// output = 10.0
+/// ```
+///
+/// A model without an exact entry falls back to the entry it is a dated snapshot of, so
+/// `gpt-4o-2024-08-06` shares the price of `gpt-4o` while `gpt-4o-mini` does not.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(transparent)]
+pub struct PriceTable(BTreeMap<String, BTreeMap<String, Price>>);
+
+impl PriceTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn price<P: AsRef<str>, M: AsRef<str>>(
+        mut self,
+        provider: P,
+        model: M,
+        price: Price,
+    ) -> Self {
+        self.insert(provider, model, price);
+        self
+    }
+
+    pub fn insert<P: AsRef<str>, M: AsRef<str>>(&mut self, provider: P, model: M, price: Price) {
+        self.0
+            .entry(provider.as_ref().to_owned())
+            .or_default()
+            .insert(model.as_ref().to_owned(), price);
+    }
+
+    /// Adds the prices of `other`, replacing those for the same model.
+    pub fn extend(&mut self, other: PriceTable) {
+        for (provider, prices) in other.0 {
+            self.0.entry(provider).or_default().extend(prices);
+        }
+    }
+
+    pub fn get(&self, provider: &str, model: &str) -> Option<&Price> {
+        let prices = self.0.get(provider)?;
+        if let Some(price) = prices.get(model) {
+            return Some(price);
+        }
+        prices
+            .range::<str, _>((Bound::Unbounded, Bound::Included(model)))
+            .rev()
+            .find(|(key, _)| is_snapshot_of(model, key))
+            .map(|(_, price)| price)
+    }
+
+    pub fn cost(&self, provider: &str, model: &str, usage: &Usage) -> Option<Cost> {
+        self.get(provider, model).map(|price| price.cost(usage))
+    }
+
+    /// The cost of a completion, priced by the model that served it.
+    pub fn completion_cost(&self, provider: &str, completion: &Completion) -> Option<Cost> {
+        let model = completion.model.as_deref()?;
+        let usage = completion.usage.unwrap_or_default();
+        self.cost(provider, model, &usage)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        toml::from_str(toml).map_err(Error::decode)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Loads a `.json` file as JSON and anything else as TOML.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| Error::invalid_request(format!("'{}': {err}", path.display())))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&content),
+            _ => Self::from_toml(&content),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::usage::{CompletionTokensDetails, PromptTokensDetails};
+
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let table = PriceTable::from_toml(
+            r#"
+            [openai."gpt-4o"]
+            input = 2.5
+            cached_input = 1.25
+            output = 10.0
+
+            [openai."gpt-4o-mini"]
+            input = 0.15
+            output = 0.6
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            table.get("openai", "gpt-4o-2024-08-06"),
+            Some(&Price::new(2.5, 10.0).cached_input(1.25))
+        );
+        assert_eq!(
+            table.get("openai", "gpt-4o-mini-2024-07-18"),
+            Some(&Price::new(0.15, 0.6))
+        );
+        assert_eq!(table.get("openai", "gpt-4"), None);
+        assert_eq!(table.get("anthropic", "gpt-4o"), None);
+        let json = serde_json::to_string(&table).unwrap();
+        assert_eq!(PriceTable::from_json(&json).unwrap(), table);
+        assert!(matches!(
+            PriceTable::from_toml("[openai.x]\ninput = 'free'"),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_fallback() {
+        let table = PriceTable::new()
+            .price("openai", "gpt-4o", Price::new(2.5, 10.0))
+            .price("openai", "o1", Price::new(15.0, 60.0));
+        assert_eq!(table.get("openai", "gpt-4o-mini"), None);
+        assert_eq!(table.get("openai", "gpt-4o-mini-2024-07-18"), None);
+        assert_eq!(table.get("openai", "o1-mini"), None);
+        assert_eq!(
+            table.get("openai", "o1-2024-12-17"),
+            Some(&Price::new(15.0, 60.0))
+        );
+    }
+
+    #[test]
+    fn test_cost() {
+        let table = PriceTable::new().price(
+            "openai",
+            "o3",
+            Price::new(2.0, 8.0).cached_input(0.5).reasoning(8.0),
+        );
+        let usage = Usage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 500_000,
+            total_tokens: 1_500_000,
+            prompt_tokens_details: Some(PromptTokensDetails {
+                cached_tokens: 400_000,
+                ..Default::default()
+            }),
+            completion_tokens_details: Some(CompletionTokensDetails {
+                reasoning_tokens: 200_000,
+                ..Default::default()
+            }),
+        };
+        let cost = table.cost("openai", "o3", &usage).unwrap();
+        assert_eq!(
+            cost,
+            Cost {
+                input: 1.2,
+                cached_input: 0.2,
+                output: 2.4,
+                reasoning: 1.6,
+            }
+        );
+        let completions = [usage, usage].map(|usage| Completion {
+            model: Some("o3".to_owned()),
+            usage: Some(usage),
+            ..Default::default()
+        });
+        let total = completions
+            .iter()
+            .filter_map(|completion| table.completion_cost("openai", completion))
+            .sum::<Cost>();
+        assert!((total.total() - 10.8).abs() < 1e-9);
+    }
+}
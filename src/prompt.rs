@@ -2,7 +2,19 @@ use std::ops::{Deref, DerefMut};
 
 use serde::{Deserialize, Serialize};
 
-use crate::message::{Message, Role};
+use crate::{
+    message::{Media, Message, Role},
+    Tokenizer,
+};
+
+/// Role and separator tokens wrapped around each message.
+const MESSAGE_TOKENS: usize = 3;
+/// Tokens that prime the reply.
+const REPLY_TOKENS: usize = 3;
+/// A high-detail image, as billed for a 512x512 tile plus the base.
+const IMAGE_TOKENS: usize = 765;
+/// A video by URL, whose frame count is unknown until the provider samples it.
+const VIDEO_TOKENS: usize = 8 * IMAGE_TOKENS;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(transparent)]
@@ -34,6 +46,10 @@ impl Prompt {
         self.message(Message::text(Role::Assistant, content))
     }
 
+    pub fn tool<I: AsRef<str>, T: AsRef<str>>(self, tool_call_id: I, content: T) -> Self {
+        self.message(Message::tool(tool_call_id, content))
+    }
+
     pub fn is_media(&self) -> bool {
         for message in &self.0 {
             if let Message::Media(_) = message {
@@ -42,6 +58,46 @@ impl Prompt {
         }
         false
     }
+
+    /// An estimate of the prompt tokens, including per-message overhead; images and videos
+    /// count as fixed placeholders since their size depends on the provider.
+    pub fn estimate_tokens(&self, tokenizer: &dyn Tokenizer) -> usize {
+        let count = |text: &str| tokenizer.count(text);
+        let content = |message: &Message| match message {
+            Message::Text(message) => count(&message.content),
+            Message::Tool(message) => count(&message.content),
+            Message::ToolCalls(message) => {
+                message.content.as_deref().map_or(0, count)
+                    + message
+                        .tool_calls
+                        .iter()
+                        .map(|call| count(&call.function.name) + count(&call.function.arguments))
+                        .sum::<usize>()
+            }
+            Message::Media(message) => message
+                .content
+                .iter()
+                .map(|media| match media {
+                    Media::Text(text) => count(text),
+                    Media::ImageUrl(_) => IMAGE_TOKENS,
+                    Media::Video(frames) => frames.len() * IMAGE_TOKENS,
+                    Media::VideoUrl(_) => VIDEO_TOKENS,
+                })
+                .sum(),
+        };
+        REPLY_TOKENS
+            + self
+                .0
+                .iter()
+                .map(|message| MESSAGE_TOKENS + content(message))
+                .sum::<usize>()
+    }
+}
+
+impl Default for Prompt {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl From<Vec<Message>> for Prompt {
@@ -72,3 +128,29 @@ impl IntoIterator for Prompt {
         self.0.into_iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{tokenizer::Approximate, tool::ToolCall};
+
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        let prompt = Prompt::new()
+            .system("Be brief.")
+            .message(
+                Message::media(Role::User)
+                    .text("What is this?")
+                    .image_url("https://example.com/a.png")
+                    .video(vec!["1.jpg", "2.jpg"])
+                    .into(),
+            )
+            .message(Message::tool_calls(vec![ToolCall::new("1", "look", "{}")]).into())
+            .tool("1", "a cat");
+        assert_eq!(
+            prompt.estimate_tokens(&Approximate),
+            3 + (3 + 3) + (3 + 4 + 3 * 765) + (3 + 1 + 1) + (3 + 2)
+        );
+    }
+}
@@ -0,0 +1,36 @@
+use std::{fmt::Debug, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::{Completion, Prompt, Result, Stream};
+
+/// A chat backend that [`ModelOptions`](crate::ModelOptions) resolves to.
+///
+/// The built-in backends implement it for their merged options; other crates can plug in their
+/// own through [`ModelOptions::custom`](crate::ModelOptions::custom).
+#[async_trait]
+pub trait Provider: Send + Sync {
+    async fn complete(&self, prompt: &Prompt) -> Result<Completion>;
+
+    async fn stream(&self, prompt: &Prompt) -> Result<Stream<Result<Completion>>>;
+}
+
+#[async_trait]
+impl<P: Provider + ?Sized> Provider for Arc<P> {
+    async fn complete(&self, prompt: &Prompt) -> Result<Completion> {
+        (**self).complete(prompt).await
+    }
+
+    async fn stream(&self, prompt: &Prompt) -> Result<Stream<Result<Completion>>> {
+        (**self).stream(prompt).await
+    }
+}
+
+#[derive(Clone)]
+pub struct CustomProvider(pub(crate) Arc<dyn Provider>);
+
+impl Debug for CustomProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CustomProvider")
+    }
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    /// The thinking budget used for providers that only take a token count.
+    pub fn budget_tokens(self) -> u32 {
+        match self {
+            Self::Low => 1024,
+            Self::Medium => 8192,
+            Self::High => 24576,
+        }
+    }
+
+    /// The lowest effort whose budget covers `budget_tokens`, for providers that only take an
+    /// effort level.
+    pub fn from_budget_tokens(budget_tokens: u32) -> Self {
+        [Self::Low, Self::Medium]
+            .into_iter()
+            .find(|effort| budget_tokens <= effort.budget_tokens())
+            .unwrap_or(Self::High)
+    }
+}
+
+/// A block of reasoning as Anthropic returned it. Each signature only covers its own block, so
+/// the blocks are sent back one by one and unchanged.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReasoningBlock {
+    Thinking { thinking: String, signature: String },
+    RedactedThinking { data: String },
+}
+
+/// Provider-neutral thinking controls, mapped by each backend onto its own parameters.
+///
+/// A provider that only understands an effort level or only a token budget derives the one from
+/// the other. `include_thoughts` asks for the reasoning to be returned, or with `false` for it
+/// to be left out, where the provider makes that distinction.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub struct Reasoning {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub effort: Option<ReasoningEffort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub budget_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub include_thoughts: Option<bool>,
+}
+
+impl Reasoning {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn effort(mut self, effort: ReasoningEffort) -> Self {
+        self.effort = Some(effort);
+        self
+    }
+
+    pub fn budget_tokens(mut self, budget_tokens: u32) -> Self {
+        self.budget_tokens = Some(budget_tokens);
+        self
+    }
+
+    pub fn include_thoughts(mut self, include_thoughts: bool) -> Self {
+        self.include_thoughts = Some(include_thoughts);
+        self
+    }
+
+    pub(crate) fn effort_level(&self) -> Option<ReasoningEffort> {
+        self.effort
+            .or(self.budget_tokens.map(ReasoningEffort::from_budget_tokens))
+    }
+
+    pub(crate) fn budget(&self) -> Option<u32> {
+        self.budget_tokens
+            .or(self.effort.map(ReasoningEffort::budget_tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effort_level() {
+        let effort = |budget_tokens| Reasoning::new().budget_tokens(budget_tokens).effort_level();
+        assert_eq!(effort(512), Some(ReasoningEffort::Low));
+        assert_eq!(effort(8192), Some(ReasoningEffort::Medium));
+        assert_eq!(effort(32000), Some(ReasoningEffort::High));
+        assert_eq!(
+            Reasoning::new()
+                .effort(ReasoningEffort::Low)
+                .budget_tokens(32000)
+                .effort_level(),
+            Some(ReasoningEffort::Low)
+        );
+        assert_eq!(Reasoning::new().effort_level(), None);
+    }
+}
@@ -0,0 +1,159 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::tool::schema_for;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub description: Option<String>,
+    pub schema: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub strict: Option<bool>,
+}
+
+impl ResponseFormat {
+    pub fn json_object() -> Self {
+        Self::JsonObject
+    }
+
+    /// A strict schema for `T`, named after the type.
+    pub fn json_schema_for<T: JsonSchema>() -> Self {
+        let name = T::schema_name()
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+                _ => '_',
+            })
+            .collect::<String>();
+        Self::json_schema(name, schema_for::<T>(), true)
+    }
+
+    /// In strict mode every object in `schema` is closed and all of its properties are required,
+    /// as OpenAI demands; optional fields stay optional by being nullable.
+    pub fn json_schema<T: AsRef<str>>(name: T, mut schema: Value, strict: bool) -> Self {
+        if strict {
+            close(&mut schema);
+        }
+        Self::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.as_ref().to_owned(),
+                description: None,
+                schema,
+                strict: Some(strict),
+            },
+        }
+    }
+
+    /// The format as a system instruction, for providers without a native parameter for it.
+    pub(crate) fn instruction(&self) -> Option<String> {
+        match self {
+            Self::Text => None,
+            Self::JsonObject => Some("Reply with only a JSON object.".to_owned()),
+            Self::JsonSchema { json_schema } => Some(format!(
+                "Reply with only a JSON value that matches this JSON schema:\n{}",
+                json_schema.schema
+            )),
+        }
+    }
+}
+
+fn close(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            let is_object = match object.get("type") {
+                Some(Value::String(ty)) => ty == "object",
+                Some(Value::Array(types)) => types.iter().any(|ty| ty == "object"),
+                _ => false,
+            };
+            if is_object {
+                let required = match object.get("properties") {
+                    Some(Value::Object(properties)) => {
+                        properties.keys().cloned().map(Value::String).collect()
+                    }
+                    _ => Vec::new(),
+                };
+                object.insert("required".to_owned(), Value::Array(required));
+                object.insert("additionalProperties".to_owned(), Value::Bool(false));
+            }
+            object.values_mut().for_each(close);
+        }
+        Value::Array(items) => items.iter_mut().for_each(close),
+        _ => {}
+    }
+}
+
+/// Extracts the JSON document from a reply, tolerating surrounding whitespace and a Markdown
+/// code fence around it.
+pub(crate) fn json_content(content: &str) -> &str {
+    let content = content.trim();
+    let Some(fenced) = content.strip_prefix("```") else {
+        return content;
+    };
+    let fenced = fenced.strip_suffix("```").unwrap_or(fenced);
+    let fenced = fenced.strip_prefix("json").unwrap_or(fenced);
+    fenced.trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Person {
+        name: String,
+        nickname: Option<String>,
+        address: Address,
+    }
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Address {
+        city: String,
+    }
+
+    #[test]
+    fn test_json_schema_for() {
+        let ResponseFormat::JsonSchema { json_schema } =
+            ResponseFormat::json_schema_for::<Person>()
+        else {
+            panic!("expected a JSON schema");
+        };
+        assert_eq!(json_schema.name, "Person");
+        assert_eq!(json_schema.strict, Some(true));
+        let schema = json_schema.schema;
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["required"], json!(["address", "name", "nickname"]));
+        assert_eq!(
+            schema["properties"]["address"]["additionalProperties"],
+            false
+        );
+        assert_eq!(schema["properties"]["address"]["required"], json!(["city"]));
+        assert_eq!(
+            serde_json::to_value(ResponseFormat::json_object()).unwrap(),
+            json!({ "type": "json_object" })
+        );
+    }
+
+    #[test]
+    fn test_json_content() {
+        assert_eq!(json_content(" {\"a\":1}\n"), "{\"a\":1}");
+        assert_eq!(json_content("```json\n{\"a\":1}\n```"), "{\"a\":1}");
+        assert_eq!(json_content("```\n[1]\n```"), "[1]");
+    }
+}
@@ -0,0 +1,294 @@
+use std::{future::Future, time::Duration};
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result, Stream};
+
+/// When and how often a failed request is sent again.
+///
+/// Delays grow exponentially from `base_delay` up to `max_delay`, each reduced by a random
+/// fraction of up to `jitter`; a `Retry-After` hint from the provider takes precedence, unless
+/// it exceeds `max_delay`, in which case the error is returned instead of waiting that long.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    #[serde(with = "millis")]
+    pub base_delay: Duration,
+    #[serde(with = "millis")]
+    pub max_delay: Duration,
+    pub jitter: f64,
+    pub status_codes: Vec<u16>,
+    pub codes: Vec<String>,
+    pub network: bool,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            status_codes: vec![408, 409, 429, 500, 502, 503, 504, 529],
+            codes: [
+                "rate_limit_exceeded",
+                "rate_limit_error",
+                "overloaded_error",
+                "server_error",
+                "RESOURCE_EXHAUSTED",
+                "UNAVAILABLE",
+            ]
+            .map(str::to_owned)
+            .to_vec(),
+            network: true,
+        }
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn status_codes(mut self, status_codes: Vec<u16>) -> Self {
+        self.status_codes = status_codes;
+        self
+    }
+
+    /// Provider error codes or types, such as `overloaded_error`, that are worth retrying.
+    pub fn codes<T: AsRef<str>>(mut self, codes: impl IntoIterator<Item = T>) -> Self {
+        self.codes = codes
+            .into_iter()
+            .map(|code| code.as_ref().to_owned())
+            .collect();
+        self
+    }
+
+    pub fn network(mut self, network: bool) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn is_retryable(&self, err: &Error) -> bool {
+        if let Error::Network(_) = err {
+            return self.network;
+        }
+        let Some(error) = err.api_error() else {
+            return false;
+        };
+        let status = error
+            .status
+            .is_some_and(|status| self.status_codes.contains(&status.as_u16()));
+        let code = [&error.code, &error.kind]
+            .into_iter()
+            .flatten()
+            .any(|code| self.codes.contains(code));
+        status || code
+    }
+
+    /// The delay before attempt `attempt + 1`, or `None` if `err` after `attempt` attempts is
+    /// final.
+    pub(crate) fn delay(&self, err: &Error, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.is_retryable(err) {
+            return None;
+        }
+        if let Some(retry_after) = err.retry_after() {
+            return (retry_after <= self.max_delay).then_some(retry_after);
+        }
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        Some(delay.mul_f64(1.0 - self.jitter * fastrand::f64()))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) async fn run<T, F, Fut>(policy: Option<&RetryPolicy>, mut request: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        let err = match request().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        match policy.and_then(|policy| policy.delay(&err, attempt)) {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => return Err(err),
+        }
+        attempt += 1;
+    }
+}
+
+/// Like [`run`], but an error yielded by the stream before its first item also counts as a
+/// failed attempt; once an item has been yielded the stream is handed over as is.
+pub(crate) async fn stream<T, F, Fut>(
+    policy: Option<&RetryPolicy>,
+    mut request: F,
+) -> Result<Stream<Result<T>>>
+where
+    T: Send + Sync + 'static,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Stream<Result<T>>>>,
+{
+    run(policy, || {
+        let stream = request();
+        async move {
+            let mut stream = stream.await?;
+            match stream.next().await {
+                Some(Err(err)) => Err(err),
+                Some(Ok(first)) => Ok(futures::stream::iter([Ok(first)])
+                    .chain(stream.into_inner())
+                    .into()),
+                None => Ok(stream),
+            }
+        }
+    })
+    .await
+}
+
+/// Serializes durations as milliseconds, which reads better in configuration files.
+pub(crate) mod millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(deserializer)?))
+    }
+
+    pub(crate) mod option {
+        use std::time::Duration;
+
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            duration: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match duration {
+                Some(duration) => super::serialize(duration, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::ApiError;
+
+    use super::*;
+
+    fn error(status: u16) -> Error {
+        Error::api(ApiError::new(
+            reqwest::StatusCode::from_u16(status).unwrap(),
+            "failed",
+        ))
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new()
+            .base_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_millis(300))
+            .jitter(0.0)
+            .max_attempts(4);
+        let delays = (1..=4)
+            .map(|attempt| policy.delay(&error(503), attempt))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 300]
+                .map(|ms| Some(Duration::from_millis(ms)))
+                .into_iter()
+                .chain([None])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(policy.delay(&error(400), 1), None);
+        let policy = policy.jitter(0.5);
+        let delay = policy.delay(&error(429), 1).unwrap();
+        assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let policy = RetryPolicy::new().max_delay(Duration::from_secs(30));
+        let error = |secs| {
+            Error::api(
+                ApiError::new(reqwest::StatusCode::TOO_MANY_REQUESTS, "slow down")
+                    .retry_after(Duration::from_secs(secs)),
+            )
+        };
+        assert_eq!(policy.delay(&error(12), 1), Some(Duration::from_secs(12)));
+        assert_eq!(policy.delay(&error(3600), 1), None);
+    }
+
+    #[test]
+    fn test_serde() {
+        let policy: RetryPolicy =
+            serde_json::from_value(json!({ "max_attempts": 5, "base_delay": 250 })).unwrap();
+        assert_eq!(
+            policy,
+            RetryPolicy::new()
+                .max_attempts(5)
+                .base_delay(Duration::from_millis(250))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_retries_before_first_item() {
+        let policy = RetryPolicy::new().base_delay(Duration::ZERO);
+        let mut attempts = 0;
+        let stream = stream(Some(&policy), || {
+            attempts += 1;
+            let items = if attempts == 1 {
+                vec![Err(error(529))]
+            } else {
+                vec![Ok(1), Err(error(529))]
+            };
+            async move { Ok(Stream::from(futures::stream::iter(items))) }
+        })
+        .await
+        .unwrap();
+        let items = stream.into_inner().collect::<Vec<_>>().await;
+        assert_eq!(attempts, 2);
+        assert!(matches!(items[..], [Ok(1), Err(Error::Api(_))]));
+    }
+}
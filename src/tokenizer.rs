@@ -0,0 +1,285 @@
+use std::{collections::HashMap, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{models::is_snapshot_of, Error, Result};
+
+/// Counts the tokens a model would see for a piece of text.
+pub trait Tokenizer: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Estimates about four bytes per token, for models without a known vocabulary.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Approximate;
+
+impl Tokenizer for Approximate {
+    fn count(&self, text: &str) -> usize {
+        text.len().div_ceil(4)
+    }
+}
+
+/// A byte-pair encoder over a vocabulary of merge ranks, e.g. the `cl100k_base.tiktoken` or
+/// `o200k_base.tiktoken` files of tiktoken.
+///
+/// Text is pre-split the way the cl100k pattern does (contractions, words with one leading
+/// non-letter, runs of up to three digits, punctuation and whitespace) before pieces are merged.
+#[derive(Clone, Debug)]
+pub struct Bpe {
+    ranks: HashMap<Vec<u8>, u32>,
+}
+
+impl Bpe {
+    /// Every single byte needs a rank so that any text can be encoded.
+    pub fn new(ranks: HashMap<Vec<u8>, u32>) -> Result<Self> {
+        if let Some(byte) = (0..=u8::MAX).find(|byte| !ranks.contains_key(&vec![*byte])) {
+            return Err(Error::invalid_request(format!(
+                "no rank for the byte {byte:#04x}"
+            )));
+        }
+        Ok(Self { ranks })
+    }
+
+    /// Parses the tiktoken format: one base64 encoded token and its rank per line.
+    pub fn from_tiktoken(content: &str) -> Result<Self> {
+        let mut ranks = HashMap::new();
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || Error::decode(format!("line {}: '{line}'", number + 1));
+            let (token, rank) = line.split_once(' ').ok_or_else(invalid)?;
+            let token = STANDARD.decode(token).map_err(|_| invalid())?;
+            let rank = rank.trim().parse().map_err(|_| invalid())?;
+            ranks.insert(token, rank);
+        }
+        Self::new(ranks)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| Error::invalid_request(format!("'{}': {err}", path.display())))?;
+        Self::from_tiktoken(&content)
+    }
+
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        let mut tokens = Vec::new();
+        for piece in pieces(text) {
+            self.merge(piece.as_bytes(), &mut tokens);
+        }
+        tokens
+    }
+
+    /// Merges the adjacent pair with the lowest rank until no pair has one.
+    fn merge(&self, piece: &[u8], tokens: &mut Vec<u32>) {
+        if let Some(rank) = self.ranks.get(piece) {
+            tokens.push(*rank);
+            return;
+        }
+        let mut bounds = (0..=piece.len()).collect::<Vec<_>>();
+        loop {
+            let best = bounds
+                .windows(3)
+                .enumerate()
+                .filter_map(|(i, w)| Some((*self.ranks.get(&piece[w[0]..w[2]])?, i)))
+                .min();
+            match best {
+                Some((_, i)) => {
+                    bounds.remove(i + 1);
+                }
+                None => break,
+            }
+        }
+        tokens.extend(bounds.windows(2).map(|w| self.ranks[&piece[w[0]..w[1]]]));
+    }
+}
+
+impl Tokenizer for Bpe {
+    fn count(&self, text: &str) -> usize {
+        self.encode(text).len()
+    }
+}
+
+fn is_newline(c: char) -> bool {
+    c == '\r' || c == '\n'
+}
+
+fn is_punctuation(c: char) -> bool {
+    !c.is_whitespace() && !c.is_alphanumeric()
+}
+
+/// Length in chars of a contraction such as `'s` or `'ll` at the start of `chars`.
+fn contraction(chars: &[char]) -> Option<usize> {
+    let lower = |i: usize| chars.get(i).map(|c| c.to_ascii_lowercase());
+    if chars.first() != Some(&'\'') {
+        return None;
+    }
+    match (lower(1)?, lower(2)) {
+        ('s' | 'd' | 'm' | 't', _) => Some(2),
+        ('l', Some('l')) | ('v', Some('e')) | ('r', Some('e')) => Some(3),
+        _ => None,
+    }
+}
+
+fn pieces(text: &str) -> Vec<&str> {
+    let (offsets, chars): (Vec<_>, Vec<_>) = text.char_indices().unzip();
+    let n = chars.len();
+    let offset = |i: usize| offsets.get(i).copied().unwrap_or(text.len());
+    let run = |mut j: usize, f: fn(char) -> bool| {
+        while j < n && f(chars[j]) {
+            j += 1;
+        }
+        j
+    };
+    let mut pieces = Vec::new();
+    let mut i = 0;
+    while i < n {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let end = if let Some(len) = contraction(&chars[i..]) {
+            i + len
+        } else if c.is_alphabetic()
+            || (!is_newline(c) && !c.is_alphanumeric() && next.is_some_and(char::is_alphabetic))
+        {
+            run(i + 1, char::is_alphabetic)
+        } else if c.is_numeric() {
+            (i + 1..n.min(i + 3))
+                .find(|j| !chars[*j].is_numeric())
+                .unwrap_or(n.min(i + 3))
+        } else if !c.is_whitespace() || (c == ' ' && next.is_some_and(is_punctuation)) {
+            run(run(i + 1, is_punctuation), is_newline)
+        } else {
+            let end = run(i, char::is_whitespace);
+            match (i..end).rev().find(|j| is_newline(chars[*j])) {
+                Some(newline) => newline + 1,
+                // A single space is left to lead the next word.
+                None if end < n && end - i > 1 => end - 1,
+                None => end,
+            }
+        };
+        pieces.push(&text[offset(i)..offset(end)]);
+        i = end;
+    }
+    pieces
+}
+
+/// Context windows in tokens by model name. Dated snapshots such as `gpt-4o-2024-08-06` share
+/// the window of the model they are a snapshot of; other models are unknown.
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-3.5-turbo", 16_385),
+    ("gpt-4", 8_192),
+    ("gpt-4-32k", 32_768),
+    ("gpt-4-1106-preview", 128_000),
+    ("gpt-4-0125-preview", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4-turbo-preview", 128_000),
+    ("gpt-4o", 128_000),
+    ("gpt-4o-mini", 128_000),
+    ("gpt-4.1", 1_047_576),
+    ("gpt-4.1-mini", 1_047_576),
+    ("gpt-4.1-nano", 1_047_576),
+    ("gpt-4.5-preview", 128_000),
+    ("gpt-5", 400_000),
+    ("gpt-5-mini", 400_000),
+    ("gpt-5-nano", 400_000),
+    ("o1", 200_000),
+    ("o1-mini", 128_000),
+    ("o1-preview", 128_000),
+    ("o3", 200_000),
+    ("o3-mini", 200_000),
+    ("o4-mini", 200_000),
+    ("claude-3-haiku", 200_000),
+    ("claude-3-opus", 200_000),
+    ("claude-3-5-haiku", 200_000),
+    ("claude-3-5-sonnet", 200_000),
+    ("claude-3-7-sonnet", 200_000),
+    ("claude-sonnet-4", 200_000),
+    ("claude-sonnet-4-5", 200_000),
+    ("claude-opus-4", 200_000),
+    ("claude-opus-4-1", 200_000),
+    ("claude-haiku-4-5", 200_000),
+    ("gemini-1.5-flash", 1_048_576),
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-2.0-flash", 1_048_576),
+    ("gemini-2.0-flash-lite", 1_048_576),
+    ("gemini-2.5-flash", 1_048_576),
+    ("gemini-2.5-flash-lite", 1_048_576),
+    ("gemini-2.5-pro", 1_048_576),
+    ("deepseek-chat", 128_000),
+    ("deepseek-reasoner", 128_000),
+    ("qwen-max", 32_768),
+    ("qwen-plus", 131_072),
+    ("qwen-turbo", 1_000_000),
+];
+
+pub fn context_window(model: &str) -> Option<usize> {
+    CONTEXT_WINDOWS
+        .iter()
+        .filter(|(name, _)| model == *name || is_snapshot_of(model, name))
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, tokens)| *tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// All single bytes plus a few merges, ranked after the bytes.
+    fn bpe(merges: &[&str]) -> Bpe {
+        let mut content = (0..=u8::MAX)
+            .map(|byte| format!("{} {byte}\n", STANDARD.encode([byte])))
+            .collect::<String>();
+        for (i, merge) in merges.iter().enumerate() {
+            content += &format!("{} {}\n", STANDARD.encode(merge), 256 + i);
+        }
+        Bpe::from_tiktoken(&content).unwrap()
+    }
+
+    #[test]
+    fn test_pieces() {
+        assert_eq!(
+            pieces("Hello world, it's 12345!\n\n  (ok)   done"),
+            [
+                "Hello", " world", ",", " it", "'s", " ", "123", "45", "!\n\n", " ", " (", "ok",
+                ")", "  ", " done"
+            ]
+        );
+        assert_eq!(pieces("héllo  "), ["héllo", "  "]);
+    }
+
+    #[test]
+    fn test_encode() {
+        let bpe = bpe(&["ll", "he", "llo", "hello", " w"]);
+        assert_eq!(bpe.encode("hello"), [259]);
+        assert_eq!(bpe.encode("hello world"), [259, 260, 111, 114, 108, 100]);
+        assert_eq!(bpe.count("yellow"), 4);
+        assert_eq!(bpe.encode("é"), [0xc3, 0xa9]);
+    }
+
+    #[test]
+    fn test_from_tiktoken() {
+        assert!(matches!(
+            Bpe::from_tiktoken("IQ== 0\n"),
+            Err(Error::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            Bpe::from_tiktoken("IQ==\n"),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn test_context_window() {
+        assert_eq!(context_window("gpt-4o-2024-08-06"), Some(128_000));
+        assert_eq!(context_window("gpt-4-0613"), Some(8_192));
+        assert_eq!(context_window("gpt-4.5-preview"), Some(128_000));
+        assert_eq!(context_window("gpt-4.5-preview-2025-02-27"), Some(128_000));
+        assert_eq!(context_window("o1-preview"), Some(128_000));
+        assert_eq!(context_window("o1-mini-2024-09-12"), Some(128_000));
+        assert_eq!(context_window("o1-2024-12-17"), Some(200_000));
+        assert_eq!(context_window("claude-sonnet-4-5-20250929"), Some(200_000));
+        assert_eq!(context_window("gpt-4o-audio-preview"), None);
+        assert_eq!(context_window("llama3"), None);
+    }
+}
@@ -0,0 +1,478 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use schemars::{generate::SchemaSettings, JsonSchema};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolType {
+    #[default]
+    Function,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ToolDefinition {
+    #[serde(rename = "type", default)]
+    pub ty: ToolType,
+    pub function: FunctionDefinition,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "empty_parameters")]
+    pub parameters: Value,
+}
+
+fn empty_parameters() -> Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+impl ToolDefinition {
+    pub fn new<T: AsRef<str>>(name: T) -> Self {
+        Self {
+            ty: ToolType::Function,
+            function: FunctionDefinition {
+                name: name.as_ref().to_owned(),
+                description: None,
+                parameters: empty_parameters(),
+            },
+        }
+    }
+
+    pub fn description<T: AsRef<str>>(mut self, description: T) -> Self {
+        self.function.description = Some(description.as_ref().to_owned());
+        self
+    }
+
+    pub fn parameters(mut self, parameters: Value) -> Self {
+        self.function.parameters = parameters;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.function.name
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct ToolCall {
+    #[serde(default)]
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub ty: ToolType,
+    #[serde(default)]
+    pub function: FunctionCall,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct FunctionCall {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+impl ToolCall {
+    pub fn new<I: AsRef<str>, N: AsRef<str>, A: AsRef<str>>(id: I, name: N, arguments: A) -> Self {
+        Self {
+            id: id.as_ref().to_owned(),
+            ty: ToolType::Function,
+            function: FunctionCall {
+                name: name.as_ref().to_owned(),
+                arguments: arguments.as_ref().to_owned(),
+            },
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.function.name
+    }
+
+    pub fn arguments(&self) -> &str {
+        &self.function.arguments
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub function: Option<FunctionCallDelta>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct FunctionCallDelta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+/// Assembles streamed [`ToolCallDelta`] fragments into complete tool calls, keyed by `index`.
+#[derive(Clone, Default, Debug)]
+pub struct ToolCallAccumulator {
+    tool_calls: BTreeMap<usize, ToolCall>,
+}
+
+impl ToolCallAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, delta: &ToolCallDelta) {
+        let tool_call = self.tool_calls.entry(delta.index).or_default();
+        if let Some(id) = delta.id.as_deref().filter(|id| !id.is_empty()) {
+            tool_call.id = id.to_owned();
+        }
+        if let Some(function) = &delta.function {
+            if let Some(name) = function.name.as_deref().filter(|name| !name.is_empty()) {
+                tool_call.function.name = name.to_owned();
+            }
+            if let Some(arguments) = &function.arguments {
+                tool_call.function.arguments += arguments.as_str();
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tool_calls.is_empty()
+    }
+
+    /// The tool calls assembled so far, whose arguments may still be incomplete.
+    pub fn tool_calls(&self) -> Vec<&ToolCall> {
+        self.tool_calls.values().collect()
+    }
+
+    /// Consumes the accumulator, failing if the arguments of any tool call are not valid JSON.
+    pub fn finish(self) -> crate::Result<Vec<ToolCall>> {
+        self.tool_calls
+            .into_values()
+            .map(|mut tool_call| {
+                if tool_call.function.arguments.trim().is_empty() {
+                    tool_call.function.arguments = "{}".to_owned();
+                }
+                if let Err(err) = serde_json::from_str::<Value>(&tool_call.function.arguments) {
+                    return Err(crate::Error::decode(format!(
+                        "arguments of tool call '{}' ({}) are not valid JSON: {}: {}",
+                        tool_call.function.name, tool_call.id, err, tool_call.function.arguments
+                    )));
+                }
+                Ok(tool_call)
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn schema_for<T: JsonSchema>() -> Value {
+    let mut schema = SchemaSettings::draft2020_12()
+        .with(|settings| {
+            settings.meta_schema = None;
+            settings.inline_subschemas = true;
+        })
+        .into_generator()
+        .into_root_schema_for::<T>();
+    schema.remove("title");
+    schema.to_value()
+}
+
+#[async_trait]
+pub trait Tool: Send + Sync + 'static {
+    type Args: DeserializeOwned + JsonSchema + Send;
+    type Output: Serialize;
+
+    fn name(&self) -> &str;
+
+    fn description(&self) -> Option<&str> {
+        None
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        let definition = ToolDefinition::new(self.name()).parameters(schema_for::<Self::Args>());
+        match self.description() {
+            Some(description) => definition.description(description),
+            None => definition,
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> anyhow::Result<Self::Output>;
+}
+
+type ToolHandler = Arc<dyn Fn(&str) -> BoxFuture<'static, Result<Value, ToolError>> + Send + Sync>;
+
+#[derive(Clone)]
+struct RegisteredTool {
+    definition: ToolDefinition,
+    handler: ToolHandler,
+}
+
+enum ToolError {
+    Arguments(serde_json::Error),
+    Call(anyhow::Error),
+}
+
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: HashMap<String, RegisteredTool>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T: Tool>(self, tool: T) -> Self {
+        let definition = tool.definition();
+        let tool = Arc::new(tool);
+        self.handler(definition, move |arguments| {
+            let tool = tool.clone();
+            let args = serde_json::from_str::<T::Args>(arguments);
+            Box::pin(async move {
+                let output = tool.call(args.map_err(ToolError::Arguments)?).await;
+                serde_json::to_value(output.map_err(ToolError::Call)?)
+                    .map_err(|err| ToolError::Call(err.into()))
+            })
+        })
+    }
+
+    pub fn function<F, Fut>(self, definition: ToolDefinition, handler: F) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<String>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.handler(definition, move |arguments| {
+            let handler = handler.clone();
+            let arguments = serde_json::from_str::<Value>(arguments);
+            Box::pin(async move {
+                let output = handler(arguments.map_err(ToolError::Arguments)?).await;
+                output.map(Value::String).map_err(ToolError::Call)
+            })
+        })
+    }
+
+    fn handler<F>(mut self, definition: ToolDefinition, handler: F) -> Self
+    where
+        F: Fn(&str) -> BoxFuture<'static, Result<Value, ToolError>> + Send + Sync + 'static,
+    {
+        self.tools.insert(
+            definition.name().to_owned(),
+            RegisteredTool {
+                definition,
+                handler: Arc::new(handler),
+            },
+        );
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions = self
+            .tools
+            .values()
+            .map(|tool| tool.definition.clone())
+            .collect::<Vec<_>>();
+        definitions.sort_by(|a, b| a.name().cmp(b.name()));
+        definitions
+    }
+
+    /// Runs `tool_call` and renders its outcome as the content of the tool message sent back to
+    /// the model; failures are reported as `{"error": ...}` objects so the model can recover.
+    pub async fn call(&self, tool_call: &ToolCall) -> String {
+        let Some(tool) = self.tools.get(tool_call.name()) else {
+            let mut names = self.tools.keys().collect::<Vec<_>>();
+            names.sort();
+            return json!({
+                "error": format!("unknown tool '{}'", tool_call.name()),
+                "tools": names,
+            })
+            .to_string();
+        };
+        let arguments = match tool_call.arguments().trim() {
+            "" => "{}",
+            arguments => arguments,
+        };
+        match (tool.handler)(arguments).await {
+            Ok(Value::String(content)) => content,
+            Ok(output) => output.to_string(),
+            Err(ToolError::Arguments(err)) => json!({
+                "error": format!("invalid arguments for tool '{}'", tool_call.name()),
+                "details": err.to_string(),
+                "parameters": tool.definition.function.parameters,
+            })
+            .to_string(),
+            Err(ToolError::Call(err)) => json!({
+                "error": format!("tool '{}' failed", tool_call.name()),
+                "details": format!("{err:#}"),
+            })
+            .to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ser_tool_definition() {
+        let tool = ToolDefinition::new("get_weather")
+            .description("Get the current weather of a city")
+            .parameters(serde_json::json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"]
+            }));
+        let json = serde_json::to_value(&tool).unwrap();
+        assert_eq!(json["type"], "function");
+        assert_eq!(json["function"]["name"], "get_weather");
+        assert_eq!(json["function"]["parameters"]["required"][0], "city");
+    }
+
+    #[test]
+    fn test_accumulate_tool_call_deltas() {
+        let chunks = [
+            r#"[{"index":0,"id":"call_0","type":"function","function":{"name":"search","arguments":""}}]"#,
+            r#"[{"index":0,"function":{"arguments":"{\"query\":"}},{"index":1,"id":"call_1","function":{"name":"now","arguments":""}}]"#,
+            r#"[{"index":0,"function":{"arguments":"\"rust\"}"}}]"#,
+        ];
+        let mut accumulator = ToolCallAccumulator::new();
+        for chunk in chunks {
+            for delta in serde_json::from_str::<Vec<ToolCallDelta>>(chunk).unwrap() {
+                accumulator.push(&delta);
+            }
+        }
+        assert_eq!(accumulator.tool_calls()[0].name(), "search");
+        let tool_calls = accumulator.finish().unwrap();
+        assert_eq!(
+            tool_calls[0],
+            ToolCall::new("call_0", "search", r#"{"query":"rust"}"#)
+        );
+        assert_eq!(tool_calls[1], ToolCall::new("call_1", "now", "{}"));
+    }
+
+    #[test]
+    fn test_accumulate_invalid_arguments() {
+        let mut accumulator = ToolCallAccumulator::new();
+        accumulator.push(&ToolCallDelta {
+            index: 0,
+            id: Some("call_0".to_owned()),
+            function: Some(FunctionCallDelta {
+                name: Some("search".to_owned()),
+                arguments: Some(r#"{"query":"#.to_owned()),
+            }),
+        });
+        let err = accumulator.finish().unwrap_err();
+        assert!(err.to_string().contains("'search' (call_0)"));
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    struct WeatherArgs {
+        /// Name of the city
+        city: String,
+        #[serde(default)]
+        unit: Option<Unit>,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Unit {
+        Celsius,
+        Fahrenheit,
+    }
+
+    struct Weather;
+
+    #[async_trait]
+    impl Tool for Weather {
+        type Args = WeatherArgs;
+        type Output = Value;
+
+        fn name(&self) -> &str {
+            "get_weather"
+        }
+
+        fn description(&self) -> Option<&str> {
+            Some("Get the current weather of a city")
+        }
+
+        async fn call(&self, args: WeatherArgs) -> anyhow::Result<Value> {
+            let temperature = match args.unit {
+                Some(Unit::Fahrenheit) => 70,
+                _ => 21,
+            };
+            Ok(json!({ "city": args.city, "temperature": temperature }))
+        }
+    }
+
+    #[test]
+    fn test_tool_definition_schema() {
+        let definition = Weather.definition();
+        let parameters = &definition.function.parameters;
+        assert_eq!(parameters["type"], "object");
+        assert_eq!(parameters["required"], json!(["city"]));
+        assert_eq!(
+            parameters["properties"]["city"]["description"],
+            "Name of the city"
+        );
+        assert!(parameters.get("$schema").is_none());
+        assert!(parameters.get("$defs").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_registry_call() {
+        let registry = ToolRegistry::new().register(Weather);
+        let content = registry
+            .call(&ToolCall::new(
+                "call_0",
+                "get_weather",
+                r#"{"city":"Hangzhou","unit":"fahrenheit"}"#,
+            ))
+            .await;
+        assert_eq!(content, r#"{"city":"Hangzhou","temperature":70}"#);
+
+        let content = registry
+            .call(&ToolCall::new(
+                "call_1",
+                "get_weather",
+                r#"{"town":"Hangzhou"}"#,
+            ))
+            .await;
+        let error = serde_json::from_str::<Value>(&content).unwrap();
+        assert_eq!(error["error"], "invalid arguments for tool 'get_weather'");
+        assert!(error["details"]
+            .as_str()
+            .unwrap()
+            .contains("missing field `city`"));
+
+        let registry = registry
+            .function(ToolDefinition::new("zoom"), |_| async { Ok(String::new()) })
+            .function(ToolDefinition::new("clock"), |_| async {
+                Ok(String::new())
+            });
+        let content = registry
+            .call(&ToolCall::new("call_2", "search", "{}"))
+            .await;
+        let error = serde_json::from_str::<Value>(&content).unwrap();
+        assert_eq!(error["tools"], json!(["clock", "get_weather", "zoom"]));
+    }
+}
@@ -1,8 +1,173 @@
-use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
 pub struct Usage {
-    pub prompt_tokens: i32,
-    pub completion_tokens: i32,
-    pub total_tokens: i32,
+    #[serde(default, deserialize_with = "count")]
+    pub prompt_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub completion_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub total_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+/// Parts of `prompt_tokens`.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct PromptTokensDetails {
+    #[serde(default, deserialize_with = "count")]
+    pub cached_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub audio_tokens: u64,
+}
+
+/// Parts of `completion_tokens`.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct CompletionTokensDetails {
+    #[serde(default, deserialize_with = "count")]
+    pub reasoning_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub audio_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub accepted_prediction_tokens: u64,
+    #[serde(default, deserialize_with = "count")]
+    pub rejected_prediction_tokens: u64,
+}
+
+/// Compatible servers send `null` for counts they do not track.
+fn count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+impl Usage {
+    pub fn cached_tokens(&self) -> u64 {
+        self.prompt_tokens_details
+            .map(|details| details.cached_tokens)
+            .unwrap_or_default()
+    }
+
+    pub fn reasoning_tokens(&self) -> u64 {
+        self.completion_tokens_details
+            .map(|details| details.reasoning_tokens)
+            .unwrap_or_default()
+    }
+}
+
+fn add_details<T: Add<Output = T> + Default>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+    }
+}
+
+impl Add for PromptTokensDetails {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            cached_tokens: self.cached_tokens + other.cached_tokens,
+            audio_tokens: self.audio_tokens + other.audio_tokens,
+        }
+    }
+}
+
+impl Add for CompletionTokensDetails {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            reasoning_tokens: self.reasoning_tokens + other.reasoning_tokens,
+            audio_tokens: self.audio_tokens + other.audio_tokens,
+            accepted_prediction_tokens: self.accepted_prediction_tokens
+                + other.accepted_prediction_tokens,
+            rejected_prediction_tokens: self.rejected_prediction_tokens
+                + other.rejected_prediction_tokens,
+        }
+    }
+}
+
+impl Add for Usage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+            prompt_tokens_details: add_details(
+                self.prompt_tokens_details,
+                other.prompt_tokens_details,
+            ),
+            completion_tokens_details: add_details(
+                self.completion_tokens_details,
+                other.completion_tokens_details,
+            ),
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl std::iter::Sum for Usage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_der_details() {
+        let usage: Usage = serde_json::from_value(json!({
+            "prompt_tokens": 2006,
+            "completion_tokens": 300,
+            "total_tokens": 2306,
+            "prompt_tokens_details": {"cached_tokens": 1920, "audio_tokens": null},
+            "completion_tokens_details": {"reasoning_tokens": 192, "accepted_prediction_tokens": 0},
+        }))
+        .unwrap();
+        assert_eq!(usage.cached_tokens(), 1920);
+        assert_eq!(usage.reasoning_tokens(), 192);
+    }
+
+    #[test]
+    fn test_add() {
+        let step = Usage {
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            total_tokens: 15,
+            prompt_tokens_details: Some(PromptTokensDetails {
+                cached_tokens: 4,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut total = Usage::default();
+        total += step;
+        total += Usage {
+            completion_tokens_details: Some(CompletionTokensDetails {
+                reasoning_tokens: 3,
+                ..Default::default()
+            }),
+            ..step
+        };
+        assert_eq!(total.total_tokens, 30);
+        assert_eq!(total.cached_tokens(), 8);
+        assert_eq!(total.reasoning_tokens(), 3);
+        assert_eq!([step, step].into_iter().sum::<Usage>(), step + step);
+    }
 }